@@ -0,0 +1,52 @@
+//times full renders of a generated tree: `cargo bench --bench render`
+//stdout goes to /dev/null so the measurement is groot's own work, not a terminal
+
+use std::{
+    fs,
+    path::Path,
+    process::{Command, Stdio},
+    time::{Duration, Instant},
+};
+
+const DIRS: usize = 200;
+const FILES_PER_DIR: usize = 250;
+const RUNS: u32 = 5;
+
+fn time_render(root: &Path, extra_args: &[&str]) -> Duration {
+    let mut total = Duration::ZERO;
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        let status = Command::new(env!("CARGO_BIN_EXE_groot"))
+            .arg(root)
+            .arg("--no-config")
+            .args(extra_args)
+            .stdout(Stdio::null())
+            .status()
+            .unwrap();
+        total += start.elapsed();
+        assert!(status.success());
+    }
+    total / RUNS
+}
+
+fn main() {
+    let root = std::env::temp_dir().join(format!("groot-bench-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    for d in 0..DIRS {
+        let dir = root.join(format!("dir{d:03}"));
+        fs::create_dir_all(&dir).unwrap();
+        for f in 0..FILES_PER_DIR {
+            fs::write(dir.join(format!("file{f:03}.txt")), "").unwrap();
+        }
+    }
+
+    let entries = DIRS * (FILES_PER_DIR + 1);
+    for extra_args in [&[][..], &["--names-only"][..]] {
+        println!(
+            "render {entries} entries {extra_args:?}: {:?} per run (mean of {RUNS})",
+            time_render(&root, extra_args)
+        );
+    }
+
+    fs::remove_dir_all(&root).unwrap();
+}
@@ -0,0 +1,104 @@
+use std::{fs, path::Path};
+
+use anyhow::{Context, Result};
+
+use crate::{root_name, Directory, File, FileKind, TreeEntry, WalkOptions};
+
+/// Builds the tree from a zip's central directory without extracting anything.
+/// Sizes are the uncompressed sizes recorded in the archive; there is no other metadata.
+/// Hidden names and excludes are filtered the same way as on disk.
+pub fn read_zip(path: &Path, options: &WalkOptions) -> Result<Directory> {
+    let file = fs::File::open(path).context(format!("unable to open archive: {path:#?}"))?;
+    let mut archive =
+        zip::ZipArchive::new(file).context(format!("unable to read archive: {path:#?}"))?;
+
+    let mut root = Directory {
+        name: root_name(path),
+        subdirectories: Vec::new(),
+        error: None,
+        skipped: None,
+    };
+    for i in 0..archive.len() {
+        let entry = archive.by_index_raw(i)?;
+        let name = entry.name()?;
+        let mut components: Vec<&str> = name.split('/').filter(|c| !c.is_empty()).collect();
+        let leaf = match components.pop() {
+            Some(leaf) => leaf,
+            None => continue,
+        };
+
+        let mut dir = &mut root;
+        let mut skipped = false;
+        for component in components {
+            if skips(options, component, true) {
+                skipped = true;
+                break;
+            }
+            dir = dir.child_dir(component);
+        }
+        if skipped || skips(options, leaf, entry.is_dir()) {
+            continue;
+        }
+
+        if entry.is_dir() {
+            dir.child_dir(leaf);
+        } else {
+            dir.subdirectories.push(TreeEntry::FileNode(File {
+                name: leaf.to_string(),
+                path: path.join(&*name),
+                size: Some(entry.size()),
+                metadata: None,
+                kind: FileKind::Regular,
+            }));
+        }
+    }
+    Ok(root)
+}
+
+//the walk's filters, going by name alone
+fn skips(options: &WalkOptions, name: &str, is_dir: bool) -> bool {
+    let hidden = name.starts_with('.');
+    (hidden && !options.show_hidden && !(is_dir && options.recurse_hidden_dirs))
+        || options.is_excluded(name, is_dir)
+        || !is_dir && options.is_filtered_by_extension(name)
+}
+
+#[test]
+fn zip_entries_become_a_tree() {
+    use std::io::Write;
+
+    use zip::write::SimpleFileOptions;
+
+    let path = std::env::temp_dir().join(format!("groot-{}-archive.zip", std::process::id()));
+    let mut writer = zip::ZipWriter::new(fs::File::create(&path).unwrap());
+    let stored = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    writer.add_directory("docs/", stored).unwrap();
+    writer.start_file("docs/guide.md", stored).unwrap();
+    writer.write_all(b"# guide").unwrap();
+    //no entry for src/ itself
+    writer.start_file("src/main.rs", stored).unwrap();
+    writer.write_all(b"fn main() {}").unwrap();
+    writer.start_file(".hidden", stored).unwrap();
+    writer.finish().unwrap();
+
+    let tree = read_zip(&path, &WalkOptions::default()).unwrap();
+    let names: Vec<(&str, Vec<&str>)> = tree
+        .subdirectories
+        .iter()
+        .map(|entry| match entry {
+            TreeEntry::DirNode(dir) => (
+                dir.name.as_str(),
+                dir.subdirectories.iter().map(TreeEntry::name).collect(),
+            ),
+            other => (other.name(), Vec::new()),
+        })
+        .collect();
+    assert_eq!(
+        names,
+        vec![("docs", vec!["guide.md"]), ("src", vec!["main.rs"])]
+    );
+    let summary = crate::summary::Summary::of(&tree);
+    assert_eq!((summary.files, summary.bytes), (2, 19));
+
+    fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,90 @@
+use std::{
+    collections::BTreeMap,
+    io::{self, Write},
+    path::Path,
+};
+
+use crate::{quoted, Directory, TreeEntry};
+
+/// Prints the tree's files grouped by extension instead of by directory: one section per
+/// extension, in alphabetical order, listing the `/`-separated paths relative to the root in
+/// walk order. Extensions are compared case-insensitively, and files without one come last.
+pub fn print_by_ext(out: &mut dyn Write, tree: &Directory, quote: bool) -> io::Result<()> {
+    let mut groups: BTreeMap<Option<String>, Vec<String>> = BTreeMap::new();
+    collect(tree, "", &mut groups);
+
+    //`None` sorts first in a BTreeMap but reads better at the end
+    let without_ext = groups.remove(&None);
+    let sections = groups
+        .into_iter()
+        .chain(without_ext.map(|paths| (None, paths)));
+    for (i, (ext, paths)) in sections.enumerate() {
+        if i > 0 {
+            writeln!(out)?;
+        }
+        match ext {
+            Some(ext) => writeln!(out, ".{ext} ({})", paths.len())?,
+            None => writeln!(out, "no extension ({})", paths.len())?,
+        }
+        for path in paths {
+            let path = if quote { quoted(&path) } else { path };
+            writeln!(out, "  {path}")?;
+        }
+    }
+    return Ok(());
+
+    fn collect(dir: &Directory, pre: &str, groups: &mut BTreeMap<Option<String>, Vec<String>>) {
+        for entry in dir.subdirectories.iter() {
+            let path = format!("{}{}", pre, entry.name());
+            match entry {
+                TreeEntry::DirNode(dir_entry) => collect(dir_entry, &format!("{path}/"), groups),
+                TreeEntry::FileNode(file) => {
+                    let ext = Path::new(&file.name)
+                        .extension()
+                        .map(|ext| ext.to_string_lossy().to_lowercase());
+                    groups.entry(ext).or_default().push(path);
+                }
+                TreeEntry::SymlinkNode(_) => (),
+            }
+        }
+    }
+}
+
+#[test]
+fn files_grouped_by_extension() {
+    use crate::{test_dir_node, test_file};
+
+    let tree = Directory {
+        name: "project".to_string(),
+        subdirectories: vec![
+            test_file("Makefile", None),
+            test_dir_node(
+                "assets",
+                vec![
+                    test_file("logo.png", None),
+                    test_file("photo.PNG", None),
+                    test_file(".gitkeep", None),
+                ],
+            ),
+            test_file("main.rs", None),
+        ],
+        error: None,
+        skipped: None,
+    };
+    let mut out = Vec::new();
+    print_by_ext(&mut out, &tree, false).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        ".png (2)
+  assets/logo.png
+  assets/photo.PNG
+
+.rs (1)
+  main.rs
+
+no extension (2)
+  Makefile
+  assets/.gitkeep
+"
+    );
+}
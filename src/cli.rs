@@ -0,0 +1,427 @@
+use std::{path::PathBuf, str::FromStr, time::Duration};
+
+use anyhow::{bail, Context, Result};
+
+use crate::{
+    color::{Color, ColorMode},
+    date::{DateFormat, TimeType},
+    hash::HashAlgorithm,
+    sort::{GroupOrder, SortOrder, Sorting},
+    style::Encoding,
+    LinkInfo,
+};
+
+#[derive(Debug, Default)]
+pub struct Args {
+    //more than one is shown as a single tree from their common ancestor
+    pub paths: Vec<PathBuf>,
+    pub from_stdin: bool,
+    pub diff: Option<PathBuf>,
+    pub no_config: bool,
+    pub age_color: bool,
+    pub age_fresh: Option<Duration>,
+    pub age_recent: Option<Duration>,
+    pub hash: Option<HashAlgorithm>,
+    pub as_mkdir: bool,
+    pub highlights: Vec<String>,
+    pub same_fs: bool,
+    pub opaque_bundles: bool,
+    pub yaml: bool,
+    pub json: bool,
+    //None for the default of 2
+    pub json_indent: Option<usize>,
+    pub verify: Option<PathBuf>,
+    pub pager: bool,
+    pub date: bool,
+    pub date_format: Option<DateFormat>,
+    pub link_info: LinkInfo,
+    pub resolve_links: bool,
+    pub max_symlink_resolutions: Option<usize>,
+    pub report_only: bool,
+    pub depth_histogram: bool,
+    pub all: bool,
+    pub descend_hidden: bool,
+    pub encoding: Encoding,
+    pub per_dir_limit: Option<usize>,
+    pub sample: Option<usize>,
+    pub reverse_depth: bool,
+    pub collapse_after: Option<usize>,
+    pub width: Option<usize>,
+    pub color: ColorMode,
+    pub flat: bool,
+    pub flat_abs: bool,
+    pub trim_common_prefix: bool,
+    pub by_ext: bool,
+    pub duplicates: bool,
+    pub shared_link_targets: bool,
+    pub portability_check: bool,
+    pub top: Option<usize>,
+    pub empty: bool,
+    pub names_only: bool,
+    pub no_ext: bool,
+    pub root_label: Option<String>,
+    pub time_type: TimeType,
+    pub created: bool,
+    pub confirm_large: Option<usize>,
+    pub sort: SortOrder,
+    pub quote_names: bool,
+    pub classify: bool,
+    pub detect_type: bool,
+    pub loc: bool,
+    pub sparse: bool,
+    //--disk-usage rather than --apparent-size
+    pub disk_usage: bool,
+    pub regular_only: bool,
+    pub timeout: Option<Duration>,
+    pub max_entries: Option<usize>,
+    //--on-limit truncate rather than abort
+    pub truncate_at_limit: bool,
+    pub report_hidden: bool,
+    pub dirs_first: bool,
+    pub group_order: Option<GroupOrder>,
+    pub sort_dirs: Option<SortOrder>,
+    pub sort_files: Option<SortOrder>,
+    pub tsv: bool,
+    pub markdown: bool,
+    pub posix_paths: bool,
+    pub age_summary: bool,
+    //--ext, lowercased and without dots; empty keeps every file
+    pub extensions: Vec<String>,
+    pub no_extension: bool,
+    pub prune: bool,
+    pub dirs_with_match: Option<String>,
+    //--compare-names-unicode-normalized
+    pub normalize_names: bool,
+    pub total_only: bool,
+    pub si: bool,
+    pub short_names: bool,
+    pub jsonl: bool,
+    pub guide_color: Option<Color>,
+    pub tree_chars: Option<String>,
+    pub no_guides: bool,
+    pub skip_dir_over: Option<u64>,
+    pub size_precision: Option<usize>,
+    pub size_if_over: Option<u64>,
+}
+
+impl Args {
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Self> {
+        let mut parsed = Args::default();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--from-stdin" => parsed.from_stdin = true,
+                "-a" => parsed.all = true,
+                "-F" => parsed.classify = true,
+                "--encoding" => parsed.encoding = value_of(&arg, args.next())?.parse()?,
+                "--descend-hidden" => parsed.descend_hidden = true,
+                "--diff" => parsed.diff = Some(parse_path(&value_of(&arg, args.next())?)?),
+                "--no-config" => parsed.no_config = true,
+                "--age-color" => parsed.age_color = true,
+                "--age-fresh" => {
+                    parsed.age_fresh = Some(parse_duration(&value_of(&arg, args.next())?)?)
+                }
+                "--confirm-large" => {
+                    let value = value_of(&arg, args.next())?;
+                    parsed.confirm_large = Some(
+                        value
+                            .parse()
+                            .with_context(|| format!("invalid --confirm-large: {value}"))?,
+                    )
+                }
+                "--per-dir-limit" => {
+                    let value = value_of(&arg, args.next())?;
+                    parsed.per_dir_limit = Some(
+                        value
+                            .parse()
+                            .with_context(|| format!("invalid --per-dir-limit: {value}"))?,
+                    )
+                }
+                "--reverse-depth" => parsed.reverse_depth = true,
+                "--width" => {
+                    let value = value_of(&arg, args.next())?;
+                    parsed.width = Some(
+                        value
+                            .parse()
+                            .with_context(|| format!("invalid --width: {value}"))?,
+                    )
+                }
+                "--collapse-after" => {
+                    let value = value_of(&arg, args.next())?;
+                    parsed.collapse_after = Some(
+                        value
+                            .parse()
+                            .with_context(|| format!("invalid --collapse-after: {value}"))?,
+                    )
+                }
+                "--top" => {
+                    let value = value_of(&arg, args.next())?;
+                    parsed.top = Some(
+                        value
+                            .parse()
+                            .with_context(|| format!("invalid --top: {value}"))?,
+                    )
+                }
+                "--sample" => {
+                    let value = value_of(&arg, args.next())?;
+                    parsed.sample = Some(
+                        value
+                            .parse()
+                            .with_context(|| format!("invalid --sample: {value}"))?,
+                    )
+                }
+                "--names-only" => parsed.names_only = true,
+                "--report-only" => parsed.report_only = true,
+                "--depth-histogram" => parsed.depth_histogram = true,
+                "--link-info" => {
+                    parsed.link_info = match value_of(&arg, args.next())?.as_str() {
+                        "link" => LinkInfo::Link,
+                        "target" => LinkInfo::Target,
+                        other => bail!("invalid --link-info: {other}, expected link or target"),
+                    }
+                }
+                "--bundles" => {
+                    parsed.opaque_bundles = match value_of(&arg, args.next())?.as_str() {
+                        "show" => false,
+                        "opaque" => true,
+                        other => bail!("invalid --bundles: {other}, expected show or opaque"),
+                    }
+                }
+                "--resolve-links" => parsed.resolve_links = true,
+                "--max-symlink-resolutions" => {
+                    let value = value_of(&arg, args.next())?;
+                    parsed.max_symlink_resolutions =
+                        Some(value.parse().with_context(|| {
+                            format!("invalid --max-symlink-resolutions: {value}")
+                        })?)
+                }
+                "--date" => parsed.date = true,
+                "--date-format" => parsed.date_format = Some(value_of(&arg, args.next())?.parse()?),
+                "--created" => parsed.created = true,
+                "--time-type" => parsed.time_type = value_of(&arg, args.next())?.parse()?,
+                "--pager" => parsed.pager = true,
+                "--yaml" => parsed.yaml = true,
+                "--tsv" => parsed.tsv = true,
+                "--markdown" => parsed.markdown = true,
+                "--posix-paths" => parsed.posix_paths = true,
+                "--age-summary" => parsed.age_summary = true,
+                "--total-only" => parsed.total_only = true,
+                "--si" => parsed.si = true,
+                "--short-names" => parsed.short_names = true,
+                "--jsonl" => parsed.jsonl = true,
+                "--size-precision" => {
+                    let value = value_of(&arg, args.next())?;
+                    parsed.size_precision = Some(
+                        value
+                            .parse()
+                            .with_context(|| format!("invalid --size-precision: {value}"))?,
+                    )
+                }
+                "--json-indent" => {
+                    let value = value_of(&arg, args.next())?;
+                    parsed.json_indent = Some(
+                        value
+                            .parse()
+                            .with_context(|| format!("invalid --json-indent: {value}"))?,
+                    )
+                }
+                "--skip-dir-over" => {
+                    parsed.skip_dir_over = Some(parse_size(&value_of(&arg, args.next())?)?)
+                }
+                "--size-if-over" => {
+                    parsed.size_if_over = Some(parse_size(&value_of(&arg, args.next())?)?)
+                }
+                "--no-guides" => parsed.no_guides = true,
+                "--tree-chars" => parsed.tree_chars = Some(value_of(&arg, args.next())?),
+                "--flat" => parsed.flat = true,
+                "--flat-abs" => parsed.flat_abs = true,
+                "--trim-common-prefix" => parsed.trim_common_prefix = true,
+                "--by-ext" => parsed.by_ext = true,
+                "--duplicates" => parsed.duplicates = true,
+                "--deduplicate-symlink-targets" => parsed.shared_link_targets = true,
+                "--portability-check" => parsed.portability_check = true,
+                "--empty" => parsed.empty = true,
+                "--json" => parsed.json = true,
+                "--verify" => parsed.verify = Some(parse_path(&value_of(&arg, args.next())?)?),
+                "--same-fs" => parsed.same_fs = true,
+                "--root-label" => parsed.root_label = Some(value_of(&arg, args.next())?),
+                "--sort" => parsed.sort = value_of(&arg, args.next())?.parse()?,
+                flag if flag.starts_with("--sort=") => {
+                    parsed.sort = flag["--sort=".len()..].parse()?
+                }
+                "--quote-names" => parsed.quote_names = true,
+                "--dirs-first" => parsed.dirs_first = true,
+                "--group-order" => parsed.group_order = Some(value_of(&arg, args.next())?.parse()?),
+                "--sort-dirs" => parsed.sort_dirs = Some(value_of(&arg, args.next())?.parse()?),
+                "--sort-files" => parsed.sort_files = Some(value_of(&arg, args.next())?.parse()?),
+                "--no-ext" => parsed.no_ext = true,
+                "--color" => parsed.color = value_of(&arg, args.next())?.parse()?,
+                "--guide-color" => parsed.guide_color = Some(value_of(&arg, args.next())?.parse()?),
+                flag if flag.starts_with("--color=") => {
+                    parsed.color = flag["--color=".len()..].parse()?
+                }
+                "--highlight" => parsed.highlights.push(value_of(&arg, args.next())?),
+                "--dirs-with-match" => parsed.dirs_with_match = Some(value_of(&arg, args.next())?),
+                "--no-extension" => parsed.no_extension = true,
+                "--prune" => parsed.prune = true,
+                "--compare-names-unicode-normalized" => parsed.normalize_names = true,
+                "--ext" => parsed
+                    .extensions
+                    .extend(parse_extensions(&value_of(&arg, args.next())?)),
+                "--as-mkdir" => parsed.as_mkdir = true,
+                "--detect-type" => parsed.detect_type = true,
+                "--loc" => parsed.loc = true,
+                "--sparse" => parsed.sparse = true,
+                "--disk-usage" => parsed.disk_usage = true,
+                "--apparent-size" => parsed.disk_usage = false,
+                "--regular-only" => parsed.regular_only = true,
+                "--report-hidden" => parsed.report_hidden = true,
+                "--max-entries" => {
+                    let value = value_of(&arg, args.next())?;
+                    parsed.max_entries = Some(
+                        value
+                            .parse()
+                            .with_context(|| format!("invalid --max-entries: {value}"))?,
+                    )
+                }
+                "--on-limit" => {
+                    parsed.truncate_at_limit = match value_of(&arg, args.next())?.as_str() {
+                        "abort" => false,
+                        "truncate" => true,
+                        other => bail!("invalid --on-limit: {other}, expected abort or truncate"),
+                    }
+                }
+                "--timeout" => {
+                    parsed.timeout = Some(parse_duration(&value_of(&arg, args.next())?)?)
+                }
+                "--hash" => parsed.hash = Some(value_of(&arg, args.next())?.parse()?),
+                "--age-recent" => {
+                    parsed.age_recent = Some(parse_duration(&value_of(&arg, args.next())?)?)
+                }
+                flag if flag.starts_with('-') => bail!("unknown option: {flag}"),
+                p => parsed.paths.push(parse_path(p)?),
+            }
+        }
+
+        Ok(parsed)
+    }
+
+    pub fn sorting(&self) -> Sorting {
+        Sorting {
+            order: self.sort,
+            dirs_first: self.dirs_first,
+            dirs: self.sort_dirs,
+            groups: self.group_order,
+            files: self.sort_files,
+            normalize_names: self.normalize_names,
+            time_type: self.time_type,
+        }
+    }
+}
+
+fn value_of(flag: &str, value: Option<String>) -> Result<String> {
+    value.with_context(|| format!("{flag} expects a value"))
+}
+
+/// Splits an `--ext` list like `rs, .TOML,md` into `["rs", "toml", "md"]`.
+fn parse_extensions(list: &str) -> Vec<String> {
+    list.split(',')
+        .map(|ext| ext.trim().trim_start_matches('.').to_lowercase())
+        .filter(|ext| !ext.is_empty())
+        .collect()
+}
+
+fn parse_path(p: &str) -> Result<PathBuf> {
+    PathBuf::from_str(p).context(format!("Path cannot be created from {p}"))
+}
+
+/// Parses a duration such as `90s`, `15m`, `12h`, `3d` or `2w`; a bare number is in seconds.
+fn parse_duration(value: &str) -> Result<Duration> {
+    let (number, unit) = match value.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => value.split_at(i),
+        None => (value, "s"),
+    };
+    let number: u64 = number
+        .parse()
+        .context(format!("invalid duration: {value}"))?;
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => bail!("invalid duration unit in {value}: expected s, m, h, d or w"),
+    };
+    number
+        .checked_mul(seconds)
+        .map(Duration::from_secs)
+        .with_context(|| format!("duration too large: {value}"))
+}
+
+/// Parses a size such as `800`, `64K`, `500M` or `4GiB` in binary units; a bare number is in
+/// bytes.
+fn parse_size(value: &str) -> Result<u64> {
+    let (number, unit) = match value.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => value.split_at(i),
+        None => (value, "B"),
+    };
+    let number: u64 = number.parse().context(format!("invalid size: {value}"))?;
+    let shift = match unit.to_ascii_uppercase().as_str() {
+        "B" => 0,
+        "K" | "KB" | "KIB" => 10,
+        "M" | "MB" | "MIB" => 20,
+        "G" | "GB" | "GIB" => 30,
+        "T" | "TB" | "TIB" => 40,
+        _ => bail!("invalid size unit in {value}: expected B, K, M, G or T"),
+    };
+    number
+        .checked_mul(1 << shift)
+        .with_context(|| format!("size too large: {value}"))
+}
+
+#[test]
+fn parse_sizes() {
+    assert_eq!(parse_size("800").unwrap(), 800);
+    assert_eq!(parse_size("64K").unwrap(), 64 * 1024);
+    assert_eq!(parse_size("4GiB").unwrap(), 4 << 30);
+    assert_eq!(parse_size("500mb").unwrap(), 500 << 20);
+    assert!(parse_size("1.5G").is_err());
+    assert!(parse_size("M").is_err());
+    assert!(parse_size("99999999999T").is_err());
+}
+
+#[test]
+fn parse_durations() {
+    assert_eq!(parse_duration("90").unwrap(), Duration::from_secs(90));
+    assert_eq!(
+        parse_duration("2h").unwrap(),
+        Duration::from_secs(2 * 60 * 60)
+    );
+    assert_eq!(
+        parse_duration("1w").unwrap(),
+        Duration::from_secs(7 * 24 * 60 * 60)
+    );
+    assert!(parse_duration("3y").is_err());
+    assert!(parse_duration("d").is_err());
+    assert!(parse_duration("99999999999999999w").is_err());
+}
+
+#[test]
+fn ext_lists_add_up() {
+    let args = Args::parse(
+        ["--ext", "rs, .TOML,,md", "--ext", "txt"]
+            .into_iter()
+            .map(String::from),
+    )
+    .unwrap();
+    assert_eq!(args.extensions, vec!["rs", "toml", "md", "txt"]);
+}
+
+#[test]
+fn parse_path_and_diff() {
+    let args = Args::parse(["a", "--diff", "b"].into_iter().map(String::from)).unwrap();
+    assert_eq!(args.paths, vec![PathBuf::from("a")]);
+    assert_eq!(args.diff, Some(PathBuf::from("b")));
+
+    assert!(Args::parse(["--diff"].into_iter().map(String::from)).is_err());
+}
@@ -0,0 +1,182 @@
+use std::{
+    str::FromStr,
+    time::{Duration, SystemTime},
+};
+
+use anyhow::{bail, Error};
+
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Color {
+    Green,
+    Yellow,
+    //bold magenta, for --highlight matches
+    Highlight,
+    //bold green like `ls`, for programs
+    Executable,
+    //the plain colors --guide-color can name
+    Black,
+    Red,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+    //bright black, which terminals draw as a dim gray; the default for the tree guides
+    Gray,
+}
+
+impl Color {
+    fn ansi_code(self) -> &'static str {
+        match self {
+            Color::Green => "32",
+            Color::Yellow => "33",
+            Color::Highlight => "1;35",
+            Color::Executable => "1;32",
+            Color::Black => "30",
+            Color::Red => "31",
+            Color::Blue => "34",
+            Color::Magenta => "35",
+            Color::Cyan => "36",
+            Color::White => "37",
+            Color::Gray => "90",
+        }
+    }
+}
+
+impl FromStr for Color {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "black" => Ok(Color::Black),
+            "red" => Ok(Color::Red),
+            "green" => Ok(Color::Green),
+            "yellow" => Ok(Color::Yellow),
+            "blue" => Ok(Color::Blue),
+            "magenta" => Ok(Color::Magenta),
+            "cyan" => Ok(Color::Cyan),
+            "white" => Ok(Color::White),
+            "gray" | "grey" => Ok(Color::Gray),
+            _ => bail!("unknown color: {s}, expected black, red, green, yellow, blue, magenta, cyan, white or gray"),
+        }
+    }
+}
+
+/// Wraps `text` in the ANSI escape codes for `color`.
+pub fn paint(text: &str, color: Color) -> String {
+    format!("\x1b[{}m{}\x1b[0m", color.ansi_code(), text)
+}
+
+/// `--color`: whether escape codes are written at all.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ColorMode {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl FromStr for ColorMode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(ColorMode::Auto),
+            "always" => Ok(ColorMode::Always),
+            "never" => Ok(ColorMode::Never),
+            _ => bail!("invalid --color: {s}, expected auto, always or never"),
+        }
+    }
+}
+
+/// The parts of the environment that decide `ColorMode::Auto`.
+#[derive(Debug, Default)]
+pub struct Env {
+    //NO_COLOR set to anything non-empty, see https://no-color.org
+    pub no_color: bool,
+    pub term: Option<String>,
+}
+
+impl Env {
+    pub fn from_process() -> Self {
+        Self {
+            no_color: std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty()),
+            term: std::env::var("TERM").ok(),
+        }
+    }
+}
+
+/// `Auto` colors only a terminal stdout, and not when `NO_COLOR` is set or `TERM` is `dumb`.
+pub fn should_colorize(mode: ColorMode, is_tty: bool, env: &Env) -> bool {
+    match mode {
+        ColorMode::Always => true,
+        ColorMode::Never => false,
+        ColorMode::Auto => is_tty && !env.no_color && env.term.as_deref() != Some("dumb"),
+    }
+}
+
+/// Modification-age thresholds for `--age-color`: files newer than `fresh` are green,
+/// files newer than `recent` are yellow and anything older keeps the default color.
+#[derive(Debug, Clone, Copy)]
+pub struct AgeColors {
+    pub fresh: Duration,
+    pub recent: Duration,
+}
+
+impl Default for AgeColors {
+    fn default() -> Self {
+        Self {
+            fresh: DAY,
+            recent: 7 * DAY,
+        }
+    }
+}
+
+impl AgeColors {
+    pub fn color_for(&self, modified: SystemTime, now: SystemTime) -> Option<Color> {
+        //a timestamp in the future counts as brand new
+        let age = now.duration_since(modified).unwrap_or_default();
+        if age < self.fresh {
+            Some(Color::Green)
+        } else if age < self.recent {
+            Some(Color::Yellow)
+        } else {
+            None
+        }
+    }
+}
+
+#[test]
+fn age_buckets() {
+    let now = SystemTime::now();
+    let colors = AgeColors::default();
+    assert_eq!(colors.color_for(now - DAY / 2, now), Some(Color::Green));
+    assert_eq!(colors.color_for(now - 3 * DAY, now), Some(Color::Yellow));
+    assert_eq!(colors.color_for(now - 30 * DAY, now), None);
+    assert_eq!(colors.color_for(now + DAY, now), Some(Color::Green));
+}
+
+#[test]
+fn colorize_decision() {
+    let terminal = Env {
+        no_color: false,
+        term: Some("xterm-256color".to_string()),
+    };
+    assert!(should_colorize(ColorMode::Auto, true, &terminal));
+    assert!(!should_colorize(ColorMode::Auto, false, &terminal));
+    assert!(should_colorize(ColorMode::Always, false, &terminal));
+    assert!(!should_colorize(ColorMode::Never, true, &terminal));
+
+    let dumb = Env {
+        no_color: false,
+        term: Some("dumb".to_string()),
+    };
+    assert!(!should_colorize(ColorMode::Auto, true, &dumb));
+    let no_color = Env {
+        no_color: true,
+        ..terminal
+    };
+    assert!(!should_colorize(ColorMode::Auto, true, &no_color));
+    assert!(should_colorize(ColorMode::Always, true, &no_color));
+}
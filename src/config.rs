@@ -0,0 +1,148 @@
+use std::{fs, io::ErrorKind, path::Path};
+
+use anyhow::{bail, Context, Result};
+
+pub const CONFIG_FILE: &str = ".treeignore";
+
+/// Per-project settings read from a `.treeignore` file in the walk root.
+///
+/// Every non-empty line that isn't a `#` comment is either a `key=value` display default
+/// or an exclude glob. Keys name a long option with `_` in place of `-`,
+/// so `dirs_first=true` means `--dirs-first` and `diff=../old` means `--diff ../old`.
+#[derive(Debug, Default)]
+pub struct Config {
+    pub excludes: Vec<String>,
+    pub defaults: Vec<(String, String)>,
+}
+
+impl Config {
+    pub fn load(root: &Path) -> Result<Option<Self>> {
+        //e.g. an archive being read in place of a directory
+        if root.is_file() {
+            return Ok(None);
+        }
+        let path = root.join(CONFIG_FILE);
+        match fs::read_to_string(&path) {
+            Ok(contents) => Ok(Some(Self::parse(&contents))),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err).context(format!("unable to read config: {path:#?}")),
+        }
+    }
+
+    pub fn parse(contents: &str) -> Self {
+        let mut config = Config::default();
+        for line in contents.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            match line.split_once('=') {
+                Some((key, value)) => config
+                    .defaults
+                    .push((key.trim().to_string(), value.trim().to_string())),
+                None => config.excludes.push(line.to_string()),
+            }
+        }
+        config
+    }
+}
+
+/// The long options a `key=value` default may set: how the tree is sorted and drawn. Options
+/// that pick the output, filter or walk the tree, read file contents or start other programs
+/// can't be set by a file in whatever directory is walked. None of these take a path.
+const DEFAULT_KEYS: &[&str] = &[
+    "sort",
+    "sort-dirs",
+    "sort-files",
+    "dirs-first",
+    "group-order",
+    "compare-names-unicode-normalized",
+    "encoding",
+    "tree-chars",
+    "no-guides",
+    "color",
+    "guide-color",
+    "highlight",
+    "quote-names",
+    "no-ext",
+    "age-color",
+    "age-fresh",
+    "age-recent",
+    "age-summary",
+    "date",
+    "date-format",
+    "created",
+    "time-type",
+    "size-precision",
+    "size-if-over",
+    "disk-usage",
+    "apparent-size",
+    "per-dir-limit",
+    "sample",
+    "collapse-after",
+    "width",
+    "reverse-depth",
+    "report-hidden",
+    "link-info",
+];
+
+/// Turns a `key=value` default into the command line arguments it stands for. `true` enables
+/// a flag and `false` leaves it off. Fails for a key that isn't one of the display and sort
+/// options, and for a value the option rejects.
+pub fn default_to_args(key: &str, value: &str) -> Result<Vec<String>> {
+    let option = key.replace('_', "-");
+    if !DEFAULT_KEYS.contains(&option.as_str()) {
+        bail!("unknown {CONFIG_FILE} setting: {key}={value}");
+    }
+    let flag = format!("--{option}");
+    let args = match value {
+        "true" => vec![flag],
+        "false" => Vec::new(),
+        _ => vec![flag, value.to_string()],
+    };
+    //a false default adds nothing, so the bare flag is checked instead, which rejects false
+    //for options taking a value
+    let checked = if args.is_empty() {
+        vec![format!("--{option}")]
+    } else {
+        args.clone()
+    };
+    crate::cli::Args::parse(checked.into_iter())
+        .with_context(|| format!("invalid {CONFIG_FILE} setting: {key}={value}"))?;
+    Ok(args)
+}
+
+#[test]
+fn parse_excludes_and_defaults() {
+    let config = Config::parse("# build output\ntarget\n*.log\n\ndirs_first = true\n");
+    assert_eq!(config.excludes, vec!["target", "*.log"]);
+    assert_eq!(
+        config.defaults,
+        vec![("dirs_first".to_string(), "true".to_string())]
+    );
+    assert_eq!(
+        default_to_args("dirs_first", "true").unwrap(),
+        vec!["--dirs-first"]
+    );
+    assert!(default_to_args("dirs_first", "false").unwrap().is_empty());
+    assert_eq!(
+        default_to_args("sort", "size").unwrap(),
+        vec!["--sort", "size"]
+    );
+}
+
+#[test]
+fn only_display_defaults_are_taken() {
+    let error = |key: &str, value: &str| format!("{:#}", default_to_args(key, value).unwrap_err());
+
+    //a misspelled key is caught even when false
+    assert_eq!(
+        error("dirs_frist", "false"),
+        "unknown .treeignore setting: dirs_frist=false"
+    );
+    //known options that aren't about display
+    for (key, value) in [("diff", "../old"), ("pager", "true"), ("hash", "md5")] {
+        assert!(error(key, value).starts_with("unknown"));
+    }
+    assert!(error("sort", "bogus").starts_with("invalid .treeignore setting: sort=bogus: "));
+    assert!(error("sort", "false").starts_with("invalid"));
+}
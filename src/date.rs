@@ -0,0 +1,242 @@
+use std::{
+    fs::Metadata,
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{bail, Error};
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Item {
+    Literal(String),
+    Year,
+    ShortYear,
+    Month,
+    MonthName,
+    Day,
+    Hour,
+    Minute,
+    Second,
+    Timestamp,
+}
+
+/// A validated strftime-style format for `--date-format`, always rendered in UTC.
+///
+/// Supports `%Y`, `%y`, `%m`, `%b`, `%d`, `%H`, `%M`, `%S`, `%s` (Unix seconds),
+/// `%F` (`%Y-%m-%d`), `%T` (`%H:%M:%S`) and `%%`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DateFormat(Vec<Item>);
+
+impl Default for DateFormat {
+    //ISO-8601
+    fn default() -> Self {
+        "%FT%TZ".parse().expect("default date format is valid")
+    }
+}
+
+impl FromStr for DateFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut items = Vec::new();
+        let mut literal = String::new();
+        let mut chars = s.chars();
+
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                literal.push(c);
+                continue;
+            }
+            let spec = match chars.next() {
+                Some(spec) => spec,
+                None => bail!("date format {s:?} ends with a lone %"),
+            };
+            if spec == '%' {
+                literal.push('%');
+                continue;
+            }
+            if !literal.is_empty() {
+                items.push(Item::Literal(std::mem::take(&mut literal)));
+            }
+            match spec {
+                'Y' => items.push(Item::Year),
+                'y' => items.push(Item::ShortYear),
+                'm' => items.push(Item::Month),
+                'b' => items.push(Item::MonthName),
+                'd' => items.push(Item::Day),
+                'H' => items.push(Item::Hour),
+                'M' => items.push(Item::Minute),
+                'S' => items.push(Item::Second),
+                's' => items.push(Item::Timestamp),
+                'F' => items.extend([
+                    Item::Year,
+                    Item::Literal("-".into()),
+                    Item::Month,
+                    Item::Literal("-".into()),
+                    Item::Day,
+                ]),
+                'T' => items.extend([
+                    Item::Hour,
+                    Item::Literal(":".into()),
+                    Item::Minute,
+                    Item::Literal(":".into()),
+                    Item::Second,
+                ]),
+                _ => bail!("unsupported specifier %{spec} in date format {s:?}"),
+            }
+        }
+        if !literal.is_empty() {
+            items.push(Item::Literal(literal));
+        }
+
+        Ok(DateFormat(items))
+    }
+}
+
+impl DateFormat {
+    pub fn format(&self, time: SystemTime) -> String {
+        let seconds = match time.duration_since(UNIX_EPOCH) {
+            Ok(since) => since.as_secs() as i64,
+            Err(before) => -(before.duration().as_secs() as i64),
+        };
+        let days = seconds.div_euclid(86_400);
+        let day_seconds = seconds.rem_euclid(86_400);
+        let (year, month, day) = civil_from_days(days);
+
+        let mut formatted = String::new();
+        for item in &self.0 {
+            match item {
+                Item::Literal(text) => formatted.push_str(text),
+                Item::Year => formatted.push_str(&format!("{year:04}")),
+                Item::ShortYear => formatted.push_str(&format!("{:02}", year.rem_euclid(100))),
+                Item::Month => formatted.push_str(&format!("{month:02}")),
+                Item::MonthName => formatted.push_str(MONTHS[month as usize - 1]),
+                Item::Day => formatted.push_str(&format!("{day:02}")),
+                Item::Hour => formatted.push_str(&format!("{:02}", day_seconds / 3600)),
+                Item::Minute => formatted.push_str(&format!("{:02}", day_seconds % 3600 / 60)),
+                Item::Second => formatted.push_str(&format!("{:02}", day_seconds % 60)),
+                Item::Timestamp => formatted.push_str(&seconds.to_string()),
+            }
+        }
+        formatted
+    }
+}
+
+/// Which timestamp `--time-type` reads for `--date`, `--age-color`, `--age-summary` and
+/// `--sort mtime`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum TimeType {
+    #[default]
+    Modified,
+    Accessed,
+    //inode change time, Unix only
+    Changed,
+    //creation time, where the platform and filesystem record it
+    Born,
+}
+
+impl FromStr for TimeType {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mtime" => Ok(TimeType::Modified),
+            "atime" => Ok(TimeType::Accessed),
+            "ctime" => Ok(TimeType::Changed),
+            "btime" => Ok(TimeType::Born),
+            _ => bail!("invalid --time-type: {s}, expected mtime, atime, ctime or btime"),
+        }
+    }
+}
+
+impl TimeType {
+    /// `None` when the platform or filesystem doesn't keep this timestamp.
+    pub fn of(self, metadata: &Metadata) -> Option<SystemTime> {
+        match self {
+            TimeType::Modified => metadata.modified().ok(),
+            TimeType::Accessed => metadata.accessed().ok(),
+            TimeType::Born => metadata.created().ok(),
+            #[cfg(unix)]
+            TimeType::Changed => {
+                use std::{os::unix::fs::MetadataExt, time::Duration};
+
+                let since = Duration::new(
+                    metadata.ctime().unsigned_abs(),
+                    metadata.ctime_nsec() as u32,
+                );
+                if metadata.ctime() >= 0 {
+                    UNIX_EPOCH.checked_add(since)
+                } else {
+                    UNIX_EPOCH.checked_sub(since)
+                }
+            }
+            #[cfg(not(unix))]
+            TimeType::Changed => None,
+        }
+    }
+}
+
+//days since 1970-01-01 to a (year, month, day) in the proleptic Gregorian calendar,
+//after Howard Hinnant's `civil_from_days`
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[test]
+fn format_known_times() {
+    use std::time::Duration;
+
+    let time = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    assert_eq!(DateFormat::default().format(time), "2023-11-14T22:13:20Z");
+    let format: DateFormat = "%d %b %y, %H:%M (%s) 100%%".parse().unwrap();
+    assert_eq!(format.format(time), "14 Nov 23, 22:13 (1700000000) 100%");
+    assert_eq!(
+        DateFormat::default().format(UNIX_EPOCH - Duration::from_secs(1)),
+        "1969-12-31T23:59:59Z"
+    );
+
+    assert!("%Y-%q".parse::<DateFormat>().is_err());
+    assert!("%Y %".parse::<DateFormat>().is_err());
+}
+
+#[cfg(unix)]
+#[test]
+fn time_types_read_their_own_field() {
+    let path = std::env::temp_dir().join(format!("groot-{}-time-type", std::process::id()));
+    std::fs::write(&path, "").unwrap();
+    let metadata = std::fs::metadata(&path).unwrap();
+
+    assert_eq!(TimeType::default().of(&metadata), metadata.modified().ok());
+    assert_eq!(
+        "atime".parse::<TimeType>().unwrap().of(&metadata),
+        metadata.accessed().ok()
+    );
+    //a fresh file's inode change time is when it was written
+    let changed = TimeType::Changed.of(&metadata).unwrap();
+    let modified = metadata.modified().unwrap();
+    assert!(
+        changed
+            .max(modified)
+            .duration_since(changed.min(modified))
+            .unwrap()
+            .as_secs()
+            < 5
+    );
+    assert!("ntime".parse::<TimeType>().is_err());
+
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,287 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs::Metadata,
+    io::{self, Write},
+    mem::{discriminant, Discriminant},
+    path::Path,
+};
+
+use crate::{display_path, style::RenderStyle, Directory, TreeEntry};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DiffStatus {
+    Added,
+    Removed,
+    Changed,
+    Unchanged,
+}
+
+impl DiffStatus {
+    fn marker(&self) -> char {
+        match self {
+            DiffStatus::Added => '+',
+            DiffStatus::Removed => '-',
+            DiffStatus::Changed => '~',
+            DiffStatus::Unchanged => ' ',
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct DiffNode {
+    name: String,
+    status: DiffStatus,
+    children: Vec<DiffNode>,
+}
+
+#[derive(Debug)]
+pub struct DiffTree {
+    children: Vec<DiffNode>,
+}
+
+/// Compares two walked trees entry by entry, matching entries by name within each directory.
+/// Directories are never marked as changed themselves; their differences show up in their children.
+pub fn diff_trees(a: &Directory, b: &Directory) -> DiffTree {
+    DiffTree {
+        children: diff_entries(&a.subdirectories, &b.subdirectories),
+    }
+}
+
+//entries match when they have the same name and are the same kind of entry
+fn key(entry: &TreeEntry) -> (&str, Discriminant<TreeEntry>) {
+    (entry.name(), discriminant(entry))
+}
+
+fn diff_entries(a: &[TreeEntry], b: &[TreeEntry]) -> Vec<DiffNode> {
+    let mut nodes = Vec::new();
+    //indexed first, so big directories aren't searched once per entry
+    let mut new_by_key = HashMap::new();
+    for new in b {
+        new_by_key.entry(key(new)).or_insert(new);
+    }
+
+    for old in a {
+        let new = new_by_key.get(&key(old)).copied();
+        nodes.push(match (old, new) {
+            (TreeEntry::DirNode(old_dir), Some(TreeEntry::DirNode(new_dir))) => DiffNode {
+                name: old_dir.name.clone(),
+                status: DiffStatus::Unchanged,
+                children: diff_entries(&old_dir.subdirectories, &new_dir.subdirectories),
+            },
+            (TreeEntry::FileNode(old_file), Some(TreeEntry::FileNode(new_file))) => DiffNode {
+                name: old_file.name.clone(),
+                status: if metadata_changed(&old_file.metadata, &new_file.metadata) {
+                    DiffStatus::Changed
+                } else {
+                    DiffStatus::Unchanged
+                },
+                children: Vec::new(),
+            },
+            (TreeEntry::SymlinkNode(old_link), Some(TreeEntry::SymlinkNode(new_link))) => {
+                DiffNode {
+                    name: old_link.name.clone(),
+                    status: if old_link.target != new_link.target
+                        || metadata_changed(&old_link.link_metadata, &new_link.link_metadata)
+                    {
+                        DiffStatus::Changed
+                    } else {
+                        DiffStatus::Unchanged
+                    },
+                    children: Vec::new(),
+                }
+            }
+            _ => whole_entry(old, DiffStatus::Removed),
+        });
+    }
+
+    let old_keys: HashSet<_> = a.iter().map(key).collect();
+    for new in b {
+        if !old_keys.contains(&key(new)) {
+            nodes.push(whole_entry(new, DiffStatus::Added));
+        }
+    }
+
+    nodes
+}
+
+fn metadata_changed(a: &Option<Metadata>, b: &Option<Metadata>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => a.len() != b.len() || a.modified().ok() != b.modified().ok(),
+        (None, None) => false,
+        _ => true,
+    }
+}
+
+//an entry that exists on only one side, along with everything below it
+fn whole_entry(entry: &TreeEntry, status: DiffStatus) -> DiffNode {
+    let children = match entry {
+        TreeEntry::DirNode(dir) => dir
+            .subdirectories
+            .iter()
+            .map(|child| whole_entry(child, status))
+            .collect(),
+        _ => Vec::new(),
+    };
+    DiffNode {
+        name: entry.name().to_string(),
+        status,
+        children,
+    }
+}
+
+/// Prints the diff as a tree drawn with `style`, each line marked `+`, `-`, `~` or blank.
+pub fn print_diff(
+    out: &mut dyn Write,
+    a: &Path,
+    b: &Path,
+    diff: &DiffTree,
+    style: &RenderStyle,
+) -> io::Result<()> {
+    writeln!(out, "- {}", display_path(a))?;
+    writeln!(out, "+ {}", display_path(b))?;
+    let (added, removed, changed) = visit(out, &diff.children, "", style)?;
+    writeln!(
+        out,
+        "{} added, {} removed, {} changed",
+        added, removed, changed
+    )?;
+    return Ok(());
+
+    fn visit(
+        out: &mut dyn Write,
+        nodes: &[DiffNode],
+        pre: &str,
+        style: &RenderStyle,
+    ) -> io::Result<(usize, usize, usize)> {
+        let (mut added, mut removed, mut changed) = (0, 0, 0);
+
+        let mut remaining = nodes.len();
+
+        for node in nodes {
+            remaining -= 1;
+            let connector = if remaining == 0 {
+                &style.last
+            } else {
+                &style.tee
+            };
+            writeln!(
+                out,
+                "{} {}{}{}",
+                node.status.marker(),
+                pre,
+                connector,
+                node.name
+            )?;
+            match node.status {
+                DiffStatus::Added => added += 1,
+                DiffStatus::Removed => removed += 1,
+                DiffStatus::Changed => changed += 1,
+                DiffStatus::Unchanged => (),
+            }
+
+            let next_prefix = format!(
+                "{}{}",
+                pre,
+                if remaining == 0 {
+                    &style.space
+                } else {
+                    &style.pipe
+                }
+            );
+            let (a, r, c) = visit(out, &node.children, &next_prefix, style)?;
+            added += a;
+            removed += r;
+            changed += c;
+        }
+
+        Ok((added, removed, changed))
+    }
+}
+
+#[test]
+fn diff_trees_marks_added_removed_and_unchanged() {
+    use crate::test_file;
+
+    let a = Directory {
+        name: "a".to_string(),
+        subdirectories: vec![test_file("kept.txt", None), test_file("gone.txt", None)],
+        error: None,
+        skipped: None,
+    };
+    let b = Directory {
+        name: "b".to_string(),
+        subdirectories: vec![test_file("kept.txt", None), test_file("new.txt", None)],
+        error: None,
+        skipped: None,
+    };
+
+    let diff = diff_trees(&a, &b);
+    let statuses: Vec<_> = diff
+        .children
+        .iter()
+        .map(|node| (node.name.as_str(), &node.status))
+        .collect();
+    assert_eq!(
+        statuses,
+        vec![
+            ("kept.txt", &DiffStatus::Unchanged),
+            ("gone.txt", &DiffStatus::Removed),
+            ("new.txt", &DiffStatus::Added),
+        ]
+    );
+}
+
+#[test]
+fn nested_changes_drawn_with_the_style() {
+    use std::fs;
+
+    let dir = crate::test_dir("diff_nested");
+    for side in ["old", "new"] {
+        fs::create_dir_all(dir.join(side).join("src")).unwrap();
+        fs::write(dir.join(side).join("README"), "same").unwrap();
+    }
+    fs::write(dir.join("old").join("src").join("main.rs"), "fn main() {}").unwrap();
+    fs::write(
+        dir.join("new").join("src").join("main.rs"),
+        "fn main() { run() }",
+    )
+    .unwrap();
+    fs::write(dir.join("new").join("src").join("run.rs"), "").unwrap();
+    //the same modification time on both sides, so only changed contents count
+    for side in ["old", "new"] {
+        fs::File::options()
+            .write(true)
+            .open(dir.join(side).join("README"))
+            .unwrap()
+            .set_modified(std::time::UNIX_EPOCH)
+            .unwrap();
+    }
+    let walk = |side: &str| {
+        let mut tree = crate::walk_dir(&dir.join(side), &crate::WalkOptions::default()).unwrap();
+        crate::sort::sort_tree(&mut tree, &Default::default());
+        tree
+    };
+
+    let mut out = Vec::new();
+    print_diff(
+        &mut out,
+        Path::new("old"),
+        Path::new("new"),
+        &diff_trees(&walk("old"), &walk("new")),
+        &RenderStyle::ASCII,
+    )
+    .unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "- old
++ new
+  |-- README
+  `- src
+~    |-- main.rs
++    `- run.rs
+1 added, 0 removed, 1 changed
+"
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,227 @@
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use crate::{
+    display_path, hash, hash::HashAlgorithm, normalize, Directory, File, FileKind, TreeEntry,
+};
+
+/// Every key shared by more than one file with the paths, relative to the root, of the files
+/// that have it. Files are keyed by name, or by their content's digest with `algorithm`;
+/// files that can't be read, and FIFOs, sockets and devices, are left out then. Sorted by key,
+/// paths in walk order. `normalize_names` keys names by their NFC form.
+pub fn find_duplicates(
+    tree: &Directory,
+    algorithm: Option<HashAlgorithm>,
+    normalize_names: bool,
+) -> Vec<(String, Vec<PathBuf>)> {
+    let key = |file: &File| match algorithm {
+        Some(_) if file.kind != FileKind::Regular => None,
+        Some(algorithm) => hash::file_digest(&file.path, algorithm).ok(),
+        None => Some(normalize::comparable(&file.name, normalize_names).into_owned()),
+    };
+    let mut seen: HashMap<String, Vec<PathBuf>> = HashMap::new();
+    collect(tree, PathBuf::new(), &key, &mut seen);
+
+    let mut duplicates: Vec<_> = seen
+        .into_iter()
+        .filter(|(_, paths)| paths.len() > 1)
+        .collect();
+    duplicates.sort();
+    return duplicates;
+
+    fn collect(
+        dir: &Directory,
+        pre: PathBuf,
+        key: &dyn Fn(&File) -> Option<String>,
+        seen: &mut HashMap<String, Vec<PathBuf>>,
+    ) {
+        for entry in dir.subdirectories.iter() {
+            match entry {
+                TreeEntry::DirNode(dir_entry) => {
+                    collect(dir_entry, pre.join(&dir_entry.name), key, seen)
+                }
+                TreeEntry::FileNode(file) => {
+                    if let Some(key) = key(file) {
+                        seen.entry(key).or_default().push(pre.join(&file.name));
+                    }
+                }
+                TreeEntry::SymlinkNode(_) => (),
+            }
+        }
+    }
+}
+
+/// `--duplicates`: one section per shared name or digest listing where it occurs.
+pub fn print_duplicates(
+    out: &mut dyn Write,
+    tree: &Directory,
+    algorithm: Option<HashAlgorithm>,
+    normalize_names: bool,
+) -> io::Result<()> {
+    let duplicates = find_duplicates(tree, algorithm, normalize_names);
+    if duplicates.is_empty() {
+        return writeln!(out, "no duplicates");
+    }
+    for (i, (key, paths)) in duplicates.iter().enumerate() {
+        if i > 0 {
+            writeln!(out)?;
+        }
+        writeln!(out, "{key} ({})", paths.len())?;
+        for path in paths {
+            writeln!(out, "  {}", path.display())?;
+        }
+    }
+    Ok(())
+}
+
+/// Every resolved target that more than one symlink points to, with the paths of those links
+/// relative to the root. Links are matched on the absolute path their target resolves to, so
+/// `../docs` and `./docs` one level down count as the same. Links whose chain takes more than
+/// `max_resolutions` hops are left out. Sorted by target.
+pub fn find_shared_targets(
+    tree: &Directory,
+    max_resolutions: usize,
+) -> Vec<(PathBuf, Vec<PathBuf>)> {
+    let mut seen: HashMap<PathBuf, Vec<PathBuf>> = HashMap::new();
+    collect(tree, PathBuf::new(), max_resolutions, &mut seen);
+
+    let mut shared: Vec<_> = seen
+        .into_iter()
+        .filter(|(_, links)| links.len() > 1)
+        .collect();
+    shared.sort();
+    return shared;
+
+    fn collect(
+        dir: &Directory,
+        pre: PathBuf,
+        max_resolutions: usize,
+        seen: &mut HashMap<PathBuf, Vec<PathBuf>>,
+    ) {
+        for entry in dir.subdirectories.iter() {
+            match entry {
+                TreeEntry::DirNode(dir_entry) => {
+                    collect(dir_entry, pre.join(&dir_entry.name), max_resolutions, seen)
+                }
+                TreeEntry::SymlinkNode(link) => {
+                    if let Some(target) = link.resolved_target(max_resolutions) {
+                        seen.entry(target).or_default().push(pre.join(&link.name));
+                    }
+                }
+                TreeEntry::FileNode(_) => (),
+            }
+        }
+    }
+}
+
+/// `--deduplicate-symlink-targets`: one section per target shared by several links.
+pub fn print_shared_targets(
+    out: &mut dyn Write,
+    tree: &Directory,
+    max_resolutions: usize,
+) -> io::Result<()> {
+    let shared = find_shared_targets(tree, max_resolutions);
+    if shared.is_empty() {
+        return writeln!(out, "no shared symlink targets");
+    }
+    for (i, (target, links)) in shared.iter().enumerate() {
+        if i > 0 {
+            writeln!(out)?;
+        }
+        writeln!(out, "{} ({} links)", display_path(target), links.len())?;
+        for link in links {
+            writeln!(out, "  {}", display_path(link))?;
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn duplicates_by_name_and_content() {
+    use std::fs;
+
+    let dir = crate::test_dir("duplicates");
+    fs::create_dir_all(dir.join("a")).unwrap();
+    fs::create_dir_all(dir.join("b")).unwrap();
+    fs::write(dir.join("mod.rs"), "one").unwrap();
+    fs::write(dir.join("a").join("mod.rs"), "two").unwrap();
+    fs::write(dir.join("a").join("copy.txt"), "one").unwrap();
+    fs::write(dir.join("b").join("unique.txt"), "three").unwrap();
+    let mut tree = crate::walk_dir(&dir, &crate::WalkOptions::default()).unwrap();
+    crate::sort::sort_tree(&mut tree, &Default::default());
+
+    assert_eq!(
+        find_duplicates(&tree, None, false),
+        vec![(
+            "mod.rs".to_string(),
+            vec![PathBuf::from("a").join("mod.rs"), PathBuf::from("mod.rs")]
+        )]
+    );
+    let by_content = find_duplicates(&tree, Some(HashAlgorithm::Crc32), false);
+    assert_eq!(by_content.len(), 1);
+    assert_eq!(
+        by_content[0].1,
+        vec![PathBuf::from("a").join("copy.txt"), PathBuf::from("mod.rs")]
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn shared_targets_match_resolved_paths() {
+    use std::{fs, os::unix::fs::symlink};
+
+    let dir = crate::test_dir("shared_targets");
+    fs::create_dir_all(dir.join("lib").join("v2")).unwrap();
+    fs::create_dir(dir.join("bin")).unwrap();
+    symlink("lib/v2", dir.join("current")).unwrap();
+    symlink("../lib/v2", dir.join("bin").join("current")).unwrap();
+    symlink("../lib", dir.join("bin").join("lib")).unwrap();
+    let mut tree = crate::walk_dir(&dir, &crate::WalkOptions::default()).unwrap();
+    crate::sort::sort_tree(&mut tree, &Default::default());
+
+    assert_eq!(
+        find_shared_targets(&tree, crate::MAX_SYMLINK_RESOLUTIONS),
+        vec![(
+            dir.canonicalize().unwrap().join("lib").join("v2"),
+            vec![
+                PathBuf::from("bin").join("current"),
+                PathBuf::from("current")
+            ]
+        )]
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(feature = "unicode-normalization")]
+#[test]
+fn normalized_names_are_duplicates() {
+    use crate::{test_dir_node, test_file};
+
+    let tree = Directory {
+        name: "root".to_string(),
+        subdirectories: vec![
+            test_file("cafe\u{301}.txt", None),
+            test_dir_node("b", vec![test_file("caf\u{e9}.txt", None)]),
+        ],
+        error: None,
+        skipped: None,
+    };
+
+    assert!(find_duplicates(&tree, None, false).is_empty());
+    assert_eq!(
+        find_duplicates(&tree, None, true),
+        vec![(
+            "caf\u{e9}.txt".to_string(),
+            vec![
+                PathBuf::from("cafe\u{301}.txt"),
+                PathBuf::from("b").join("caf\u{e9}.txt")
+            ]
+        )]
+    );
+}
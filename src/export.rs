@@ -0,0 +1,200 @@
+use std::io::Write;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{hash, hash::HashAlgorithm, Directory, FileKind, TreeEntry};
+
+/// The serializable shape of a walked tree. Every node carries a `type` tag
+/// and directories hold their entries in a `children` sequence.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Node {
+    Directory {
+        name: String,
+        children: Vec<Node>,
+        //set when the directory couldn't be read, so it isn't mistaken for an empty one
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    },
+    File {
+        name: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        size: Option<u64>,
+        //the --hash digest, left out when the file can't be read
+        #[serde(skip_serializing_if = "Option::is_none")]
+        hash: Option<String>,
+    },
+    Symlink {
+        name: String,
+        target: String,
+    },
+}
+
+impl Node {
+    /// `hash`: files carry their digest with this algorithm.
+    pub fn from_dir(dir: &Directory, hash: Option<HashAlgorithm>) -> Self {
+        Node::Directory {
+            name: dir.name.clone(),
+            children: dir
+                .subdirectories
+                .iter()
+                .map(|entry| Node::from_entry(entry, hash))
+                .collect(),
+            error: dir.error.clone(),
+        }
+    }
+
+    fn from_entry(entry: &TreeEntry, algorithm: Option<HashAlgorithm>) -> Self {
+        match entry {
+            TreeEntry::DirNode(dir) => Node::from_dir(dir, algorithm),
+            TreeEntry::FileNode(file) => Node::File {
+                name: file.name.clone(),
+                size: file.size,
+                //FIFOs, sockets and devices have no contents to hash
+                hash: algorithm
+                    .filter(|_| file.kind == FileKind::Regular)
+                    .and_then(|algorithm| hash::file_digest(&file.path, algorithm).ok()),
+            },
+            TreeEntry::SymlinkNode(link) => Node::Symlink {
+                name: link.name.clone(),
+                target: link.target.clone(),
+            },
+        }
+    }
+}
+
+/// Writes the tree as JSON indented by `indent` spaces per level, or on a single line when
+/// `indent` is 0. With `hash`, files carry their digest.
+#[cfg(feature = "json")]
+pub fn print_json(
+    out: &mut dyn Write,
+    tree: &Directory,
+    indent: usize,
+    hash: Option<HashAlgorithm>,
+) -> anyhow::Result<()> {
+    let node = Node::from_dir(tree, hash);
+    if indent == 0 {
+        serde_json::to_writer(&mut *out, &node)?;
+    } else {
+        let indent = " ".repeat(indent);
+        let formatter = serde_json::ser::PrettyFormatter::with_indent(indent.as_bytes());
+        let mut serializer = serde_json::Serializer::with_formatter(&mut *out, formatter);
+        node.serialize(&mut serializer)?;
+    }
+    writeln!(out)?;
+    Ok(())
+}
+
+#[cfg(feature = "yaml")]
+pub fn print_yaml(
+    out: &mut dyn Write,
+    tree: &Directory,
+    hash: Option<HashAlgorithm>,
+) -> anyhow::Result<()> {
+    serde_yaml::to_writer(out, &Node::from_dir(tree, hash))?;
+    Ok(())
+}
+
+#[cfg(feature = "yaml")]
+#[test]
+fn yaml_nests_children() {
+    use crate::{test_dir_node, test_file};
+
+    let tree = Directory {
+        name: "root".to_string(),
+        subdirectories: vec![test_dir_node("src", vec![test_file("main.rs", None)])],
+        error: None,
+        skipped: None,
+    };
+    assert_eq!(
+        serde_yaml::to_string(&Node::from_dir(&tree, None)).unwrap(),
+        "type: directory
+name: root
+children:
+- type: directory
+  name: src
+  children:
+  - type: file
+    name: main.rs
+"
+    );
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn json_marks_unreadable_directories() {
+    use crate::test_dir_node;
+
+    let tree = Directory {
+        name: "root".to_string(),
+        subdirectories: vec![
+            test_dir_node("empty", vec![]),
+            TreeEntry::DirNode(Directory {
+                name: "locked".to_string(),
+                subdirectories: vec![],
+                error: Some("Permission denied (os error 13)".to_string()),
+                skipped: None,
+            }),
+        ],
+        error: None,
+        skipped: None,
+    };
+    let mut out = Vec::new();
+    print_json(&mut out, &tree, 0, None).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        r#"{"type":"directory","name":"root","children":[{"type":"directory","name":"empty","children":[]},{"type":"directory","name":"locked","children":[],"error":"Permission denied (os error 13)"}]}
+"#
+    );
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn json_indent_width() {
+    let tree = Directory {
+        name: "root".to_string(),
+        subdirectories: vec![TreeEntry::SymlinkNode(crate::Symlink {
+            name: "link".to_string(),
+            path: "root/link".into(),
+            target: "elsewhere".to_string(),
+            link_metadata: None,
+            target_metadata: None,
+        })],
+        error: None,
+        skipped: None,
+    };
+    let mut out = Vec::new();
+    print_json(&mut out, &tree, 4, None).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        r#"{
+    "type": "directory",
+    "name": "root",
+    "children": [
+        {
+            "type": "symlink",
+            "name": "link",
+            "target": "elsewhere"
+        }
+    ]
+}
+"#
+    );
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn json_carries_digests() {
+    use std::fs;
+
+    let dir = crate::test_dir("json_digests");
+    fs::write(dir.join("abc.txt"), "abc").unwrap();
+    let tree = crate::walk_dir(&dir, &crate::WalkOptions::default()).unwrap();
+    let mut out = Vec::new();
+    print_json(&mut out, &tree, 0, Some(HashAlgorithm::Crc32)).unwrap();
+    assert!(String::from_utf8(out)
+        .unwrap()
+        .contains(r#"{"type":"file","name":"abc.txt","size":3,"hash":"352441c2"}"#));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
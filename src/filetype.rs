@@ -0,0 +1,77 @@
+use std::{fs, io::Read, path::Path};
+
+//enough for every signature below
+const HEADER_LEN: usize = 16;
+
+/// Names the type of a file's contents from its first bytes, for `--detect-type`.
+/// `None` when the file can't be read.
+pub fn detect(path: &Path) -> Option<&'static str> {
+    let mut file = fs::File::open(path).ok()?;
+    let mut header = [0u8; HEADER_LEN];
+    let mut read = 0;
+    //a single read may return less than is there
+    while read < HEADER_LEN {
+        match file.read(&mut header[read..]).ok()? {
+            0 => break,
+            n => read += n,
+        }
+    }
+    Some(classify(&header[..read]))
+}
+
+fn classify(header: &[u8]) -> &'static str {
+    const SIGNATURES: [(&[u8], &str); 6] = [
+        (b"\x89PNG\r\n\x1a\n", "png"),
+        (b"\xff\xd8\xff", "jpeg"),
+        (b"\x7fELF", "elf"),
+        (b"PK\x03\x04", "zip"),
+        //an empty archive has only the end of central directory record
+        (b"PK\x05\x06", "zip"),
+        (b"%PDF-", "pdf"),
+    ];
+
+    if header.is_empty() {
+        return "empty";
+    }
+    for (signature, name) in SIGNATURES {
+        if header.starts_with(signature) {
+            return name;
+        }
+    }
+    if is_text(header) {
+        "text"
+    } else {
+        "binary"
+    }
+}
+
+//UTF-8 without control bytes other than whitespace; the header may cut a character in half
+fn is_text(header: &[u8]) -> bool {
+    let valid = match std::str::from_utf8(header) {
+        Ok(text) => text,
+        Err(err) if err.error_len().is_none() => {
+            std::str::from_utf8(&header[..err.valid_up_to()]).unwrap_or_default()
+        }
+        Err(_) => return false,
+    };
+    valid
+        .chars()
+        .all(|c| !c.is_control() || c.is_ascii_whitespace())
+}
+
+#[test]
+fn classify_headers() {
+    assert_eq!(classify(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"), "png");
+    assert_eq!(classify(b"\xff\xd8\xff\xe0\0\x10JFIF"), "jpeg");
+    assert_eq!(classify(b"\x7fELF\x02\x01\x01"), "elf");
+    assert_eq!(classify(b"PK\x03\x04\x14\0"), "zip");
+    assert_eq!(classify(b"%PDF-1.7\n"), "pdf");
+    assert_eq!(classify(b"#!/bin/sh\necho hi\n"), "text");
+    //a multi-byte character cut off by the header length
+    assert_eq!(
+        classify("caf\u{e9}".as_bytes().split_last().unwrap().1),
+        "text"
+    );
+    assert_eq!(classify(b"\0\x01\x02\x03"), "binary");
+    assert_eq!(classify(b""), "empty");
+}
@@ -0,0 +1,175 @@
+use std::io::{self, Write};
+
+use crate::{quoted, Directory, TreeEntry};
+
+/// Prints one entry per line in walk order as a `/`-separated path relative to the root,
+/// e.g. `src/main.rs`, with a trailing `/` on directories. With `include_root` every path
+/// starts with the root's name instead, e.g. `project/src/main.rs`. With `quote` each path is
+/// double-quoted as a whole. With `trim_prefix` the directories every path starts with are
+/// left out and printed once as a `prefix/:` header. No summary is written.
+pub fn print_flat(
+    out: &mut dyn Write,
+    tree: &Directory,
+    include_root: bool,
+    quote: bool,
+    trim_prefix: bool,
+) -> io::Result<()> {
+    let prefix = match include_root {
+        true if tree.name.ends_with('/') => tree.name.clone(),
+        true => format!("{}/", tree.name),
+        false => String::new(),
+    };
+    let mut paths = Vec::new();
+    collect(tree, &prefix, &mut paths);
+
+    let line = |path: &str| {
+        if quote {
+            quoted(path)
+        } else {
+            path.to_string()
+        }
+    };
+    let common = if trim_prefix {
+        common_dir_prefix(&paths)
+    } else {
+        0
+    };
+    if common > 0 {
+        writeln!(out, "{}:", line(&paths[0][..common]))?;
+    }
+    for path in &paths {
+        writeln!(out, "{}", line(&path[common..]))?;
+    }
+    return Ok(());
+
+    fn collect(dir: &Directory, pre: &str, paths: &mut Vec<String>) {
+        for entry in dir.subdirectories.iter() {
+            let path = format!("{}{}", pre, entry.name());
+            match entry {
+                TreeEntry::DirNode(dir_entry) => {
+                    paths.push(format!("{path}/"));
+                    collect(dir_entry, &format!("{path}/"), paths);
+                }
+                _ => paths.push(path),
+            }
+        }
+    }
+}
+
+//the length of the longest run of whole directories, up to and including a `/`, that every
+//path starts with; an entry's own name is never part of it
+fn common_dir_prefix(paths: &[String]) -> usize {
+    let parent = |path: &str| {
+        let path = path.strip_suffix('/').unwrap_or(path);
+        path.rfind('/').map_or(0, |i| i + 1)
+    };
+    let Some(first) = paths.first() else {
+        return 0;
+    };
+    let mut common = parent(first);
+    for path in &paths[1..] {
+        common = common.min(parent(path));
+        let shared = first[..common]
+            .bytes()
+            .zip(path.bytes())
+            .take_while(|(a, b)| a == b)
+            .count();
+        //by bytes, since `shared` can end inside a multi-byte character
+        common = first.as_bytes()[..shared]
+            .iter()
+            .rposition(|&byte| byte == b'/')
+            .map_or(0, |i| i + 1);
+    }
+    common
+}
+
+#[test]
+fn flat_paths_with_and_without_root() {
+    use crate::{test_dir_node, test_file};
+
+    let tree = Directory {
+        name: "project".to_string(),
+        subdirectories: vec![
+            test_dir_node("src", vec![test_file("main.rs", None)]),
+            test_dir_node("empty", vec![]),
+        ],
+        error: None,
+        skipped: None,
+    };
+    let render = |include_root| {
+        let mut out = Vec::new();
+        print_flat(&mut out, &tree, include_root, false, false).unwrap();
+        String::from_utf8(out).unwrap()
+    };
+    assert_eq!(render(false), "src/\nsrc/main.rs\nempty/\n");
+    assert_eq!(
+        render(true),
+        "project/src/\nproject/src/main.rs\nproject/empty/\n"
+    );
+}
+
+#[test]
+fn quoted_flat_paths() {
+    use crate::{test_dir_node, test_file};
+
+    let tree = Directory {
+        name: "root".to_string(),
+        subdirectories: vec![test_dir_node(
+            "my docs",
+            vec![test_file("say \"hi\"\n.txt", None)],
+        )],
+        error: None,
+        skipped: None,
+    };
+    let mut out = Vec::new();
+    print_flat(&mut out, &tree, false, true, false).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "\"my docs/\"\n\"my docs/say \\\"hi\\\"\\n.txt\"\n"
+    );
+}
+
+#[test]
+fn trimmed_common_prefix() {
+    use crate::test_dir_node;
+
+    let paths = |paths: &[&str]| {
+        paths
+            .iter()
+            .map(|path| path.to_string())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        common_dir_prefix(&paths(&[
+            "project/src/",
+            "project/src/main.rs",
+            "project/src/bin/"
+        ])),
+        "project/".len()
+    );
+    assert_eq!(
+        common_dir_prefix(&paths(&["a/src/x.rs", "a/src/y.rs"])),
+        "a/src/".len()
+    );
+    //a shared start that isn't a whole directory isn't trimmed
+    assert_eq!(common_dir_prefix(&paths(&["a/bc/x", "a/bd/y"])), "a/".len());
+    assert_eq!(
+        common_dir_prefix(&paths(&["d/caf\u{e9}/x", "d/caf\u{e8}/y"])),
+        "d/".len()
+    );
+    assert_eq!(common_dir_prefix(&paths(&["src/", "Cargo.toml"])), 0);
+    assert_eq!(common_dir_prefix(&[]), 0);
+
+    let tree = Directory {
+        name: "project".to_string(),
+        subdirectories: vec![test_dir_node("src", vec![test_dir_node("bin", vec![])])],
+        error: None,
+        skipped: None,
+    };
+    let mut out = Vec::new();
+    print_flat(&mut out, &tree, true, false, true).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "project/:\nsrc/\nsrc/bin/\n"
+    );
+}
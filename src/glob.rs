@@ -0,0 +1,124 @@
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+/// Matches `name` against a shell-style glob where `*` matches any run of characters
+/// and `?` matches exactly one character.
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+
+    let (mut p, mut n) = (0, 0);
+    //position of the last `*` seen and the name position it was tried against
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                //let the last `*` swallow one more character and retry
+                Some((star, matched)) => {
+                    p = star + 1;
+                    n = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Whether `path` has a wildcard in it, so a root given as `src/*/tests` gets expanded.
+pub fn is_pattern(path: &Path) -> bool {
+    path.to_string_lossy().contains(['*', '?'])
+}
+
+/// Every existing path matching `pattern`, a path whose components can hold wildcards, in
+/// sorted order. As with excludes, a trailing `/` matches only directories. A wildcard doesn't
+/// match a leading `.` unless its component starts with one too, like in a shell.
+pub fn expand(pattern: &Path) -> Vec<PathBuf> {
+    let dirs_only = pattern
+        .to_string_lossy()
+        .ends_with(['/', std::path::MAIN_SEPARATOR]);
+    let mut matches = vec![PathBuf::new()];
+    for component in pattern.components() {
+        let name = component.as_os_str().to_string_lossy();
+        if !matches!(component, Component::Normal(_)) || !name.contains(['*', '?']) {
+            for path in matches.iter_mut() {
+                path.push(component);
+            }
+            continue;
+        }
+        let mut next = Vec::new();
+        for dir in &matches {
+            let listing = if dir.as_os_str().is_empty() {
+                fs::read_dir(".")
+            } else {
+                fs::read_dir(dir)
+            };
+            let Ok(listing) = listing else {
+                continue;
+            };
+            let mut found: Vec<PathBuf> = listing
+                .filter_map(|entry| {
+                    let entry_name = entry.ok()?.file_name().to_string_lossy().into_owned();
+                    let hidden_ok = !entry_name.starts_with('.') || name.starts_with('.');
+                    (hidden_ok && glob_match(&name, &entry_name)).then(|| dir.join(entry_name))
+                })
+                .collect();
+            found.sort();
+            next.extend(found);
+        }
+        matches = next;
+    }
+    matches.retain(|path| {
+        if dirs_only {
+            path.is_dir()
+        } else {
+            path.exists() || path.is_symlink()
+        }
+    });
+    matches
+}
+
+#[test]
+fn glob_wildcards() {
+    assert!(glob_match("*.rs", "main.rs"));
+    assert!(glob_match("target", "target"));
+    assert!(glob_match("te?t_*", "test_output.txt"));
+    assert!(glob_match("*", ""));
+    assert!(!glob_match("*.rs", "main.rs.bak"));
+    assert!(!glob_match("?", ""));
+}
+
+#[test]
+fn expand_matches_each_component() {
+    let dir = crate::test_dir("glob-roots");
+    for sub in ["a/tests", "b/tests", "c/src", ".hidden/tests"] {
+        fs::create_dir_all(dir.join(sub)).unwrap();
+    }
+    fs::write(dir.join("d"), "").unwrap();
+
+    assert!(is_pattern(&dir.join("*").join("tests")));
+    assert!(!is_pattern(&dir));
+    assert_eq!(
+        expand(&dir.join("*").join("tests")),
+        vec![dir.join("a").join("tests"), dir.join("b").join("tests")]
+    );
+    assert_eq!(expand(&dir.join("?")).len(), 4);
+    assert_eq!(expand(&dir.join("?/")).len(), 3);
+    assert_eq!(expand(&dir.join(".*")), vec![dir.join(".hidden")]);
+    assert!(expand(&dir.join("*").join("docs")).is_empty());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
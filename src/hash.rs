@@ -0,0 +1,135 @@
+use std::{
+    fs,
+    io::{self, Read},
+    path::Path,
+    str::FromStr,
+};
+
+use anyhow::{bail, Error};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HashAlgorithm {
+    Crc32,
+    #[cfg(feature = "crypto-hash")]
+    Md5,
+    #[cfg(feature = "crypto-hash")]
+    Sha256,
+}
+
+impl FromStr for HashAlgorithm {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "crc32" => Ok(HashAlgorithm::Crc32),
+            #[cfg(feature = "crypto-hash")]
+            "md5" => Ok(HashAlgorithm::Md5),
+            #[cfg(feature = "crypto-hash")]
+            "sha256" => Ok(HashAlgorithm::Sha256),
+            #[cfg(not(feature = "crypto-hash"))]
+            "md5" | "sha256" => bail!("{s} hashing needs groot built with the crypto-hash feature"),
+            _ => bail!("unknown hash algorithm: {s}, expected crc32, md5 or sha256"),
+        }
+    }
+}
+
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                0xEDB8_8320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+enum Hasher {
+    Crc32(u32),
+    #[cfg(feature = "crypto-hash")]
+    Md5(md5::Md5),
+    #[cfg(feature = "crypto-hash")]
+    Sha256(sha2::Sha256),
+}
+
+impl Hasher {
+    fn new(algorithm: HashAlgorithm) -> Self {
+        #[cfg(feature = "crypto-hash")]
+        use sha2::Digest;
+
+        match algorithm {
+            HashAlgorithm::Crc32 => Hasher::Crc32(!0),
+            #[cfg(feature = "crypto-hash")]
+            HashAlgorithm::Md5 => Hasher::Md5(md5::Md5::new()),
+            #[cfg(feature = "crypto-hash")]
+            HashAlgorithm::Sha256 => Hasher::Sha256(sha2::Sha256::new()),
+        }
+    }
+
+    fn update(&mut self, bytes: &[u8]) {
+        #[cfg(feature = "crypto-hash")]
+        use sha2::Digest;
+
+        match self {
+            Hasher::Crc32(crc) => {
+                for &byte in bytes {
+                    *crc = CRC32_TABLE[((*crc ^ byte as u32) & 0xFF) as usize] ^ (*crc >> 8);
+                }
+            }
+            #[cfg(feature = "crypto-hash")]
+            Hasher::Md5(hasher) => hasher.update(bytes),
+            #[cfg(feature = "crypto-hash")]
+            Hasher::Sha256(hasher) => hasher.update(bytes),
+        }
+    }
+
+    fn finish(self) -> String {
+        #[cfg(feature = "crypto-hash")]
+        use sha2::Digest;
+
+        match self {
+            Hasher::Crc32(crc) => format!("{:08x}", !crc),
+            #[cfg(feature = "crypto-hash")]
+            Hasher::Md5(hasher) => to_hex(&hasher.finalize()),
+            #[cfg(feature = "crypto-hash")]
+            Hasher::Sha256(hasher) => to_hex(&hasher.finalize()),
+        }
+    }
+}
+
+#[cfg(feature = "crypto-hash")]
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Hashes the file at `path` in fixed-size chunks so large files are never held in memory.
+pub fn file_digest(path: &Path, algorithm: HashAlgorithm) -> io::Result<String> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = Hasher::new(algorithm);
+    let mut buf = [0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut buf)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buf[..read]);
+    }
+    Ok(hasher.finish())
+}
+
+#[test]
+fn crc32_check_value() {
+    let mut hasher = Hasher::new(HashAlgorithm::Crc32);
+    hasher.update(b"1234");
+    hasher.update(b"56789");
+    assert_eq!(hasher.finish(), "cbf43926");
+}
@@ -0,0 +1,190 @@
+use std::{
+    fs::Metadata,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::{
+    date::DateFormat, list_dir, root_name, sort, sort::Sorting, Directory, FileKind, TreeEntry,
+    WalkOptions,
+};
+
+//one line of output, complete on its own
+#[derive(Serialize)]
+struct Line<'a> {
+    path: &'a str,
+    #[serde(rename = "type")]
+    kind: &'static str,
+    depth: usize,
+    size: Option<u64>,
+    mtime: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<&'a str>,
+}
+
+/// Writes one JSON object per entry below `root` as soon as its directory is read, instead of
+/// building the whole tree first. Only the directories on the way to the current one are held,
+/// each sorted by `sorting` on its own. Depth 1 is directly below the root and paths are
+/// relative and `/`-separated; a directory's size and mtime are `null`, like a symlink's size.
+pub fn stream_jsonl(
+    out: &mut dyn Write,
+    root: &PathBuf,
+    options: &WalkOptions,
+    sorting: &Sorting,
+    regular_only: bool,
+) -> Result<()> {
+    let stream = Stream {
+        options,
+        sorting,
+        regular_only,
+        date_format: DateFormat::default(),
+    };
+    let (listing, descended) = stream.list(root)?;
+    stream.visit(out, root, listing, &descended, "", 1)
+}
+
+struct Stream<'a> {
+    options: &'a WalkOptions,
+    sorting: &'a Sorting,
+    regular_only: bool,
+    date_format: DateFormat,
+}
+
+impl Stream<'_> {
+    //one directory with its subdirectories left empty, along with the names of the ones the
+    //walk would have gone into
+    fn list(&self, path: &PathBuf) -> Result<(Directory, Vec<String>)> {
+        let mut descended = Vec::new();
+        let listing = list_dir(path, self.options, &mut |sub_dir| {
+            descended.push(root_name(sub_dir));
+            Ok(Directory {
+                name: root_name(sub_dir),
+                subdirectories: Vec::new(),
+                error: None,
+                skipped: None,
+            })
+        })?;
+        Ok((listing, descended))
+    }
+
+    fn visit(
+        &self,
+        out: &mut dyn Write,
+        path: &Path,
+        mut listing: Directory,
+        descended: &[String],
+        pre: &str,
+        depth: usize,
+    ) -> Result<()> {
+        //the subdirectories haven't been read yet, so this only orders the listing itself
+        sort::sort_tree(&mut listing, self.sorting);
+        let mtime = |metadata: Option<&Metadata>| {
+            metadata
+                .and_then(|metadata| metadata.modified().ok())
+                .map(|modified| self.date_format.format(modified))
+        };
+        for entry in &listing.subdirectories {
+            let relative = format!("{}{}", pre, entry.name());
+            let mut line = Line {
+                path: &relative,
+                kind: "file",
+                depth,
+                size: None,
+                mtime: None,
+                error: None,
+            };
+            match entry {
+                TreeEntry::DirNode(dir) => {
+                    let dir_path = path.join(&dir.name);
+                    let child = descended.contains(&dir.name).then(|| self.list(&dir_path));
+                    let error = match &child {
+                        Some(Err(err)) => Some(err.root_cause().to_string()),
+                        _ => dir.error.clone(),
+                    };
+                    line.kind = "directory";
+                    line.error = error.as_deref();
+                    write_line(out, &line)?;
+                    if let Some(Ok((child, child_descended))) = child {
+                        self.visit(
+                            out,
+                            &dir_path,
+                            child,
+                            &child_descended,
+                            &format!("{relative}/"),
+                            depth + 1,
+                        )?;
+                    }
+                    continue;
+                }
+                TreeEntry::FileNode(file) => {
+                    if self.regular_only && file.kind != FileKind::Regular {
+                        continue;
+                    }
+                    line.size = file.size;
+                    line.mtime = mtime(file.metadata.as_ref());
+                }
+                TreeEntry::SymlinkNode(link) => {
+                    line.kind = "symlink";
+                    line.mtime = mtime(link.link_metadata.as_ref());
+                }
+            }
+            write_line(out, &line)?;
+        }
+        Ok(())
+    }
+}
+
+fn write_line(out: &mut dyn Write, line: &Line) -> Result<()> {
+    serde_json::to_writer(&mut *out, line)?;
+    writeln!(out)?;
+    Ok(())
+}
+
+#[test]
+fn every_line_is_its_own_object() {
+    let dir = crate::test_dir("jsonl");
+    std::fs::create_dir(dir.join("src")).unwrap();
+    std::fs::write(dir.join("src").join("main.rs"), "fn main() {}").unwrap();
+    std::fs::write(dir.join("README"), "hi").unwrap();
+
+    let mut out = Vec::new();
+    stream_jsonl(
+        &mut out,
+        &dir,
+        &WalkOptions::default(),
+        &Sorting::default(),
+        false,
+    )
+    .unwrap();
+    let lines: Vec<serde_json::Value> = String::from_utf8(out)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    let fields: Vec<(&str, &str, u64, Option<u64>)> = lines
+        .iter()
+        .map(|line| {
+            (
+                line["path"].as_str().unwrap(),
+                line["type"].as_str().unwrap(),
+                line["depth"].as_u64().unwrap(),
+                line["size"].as_u64(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        fields,
+        vec![
+            ("README", "file", 1, Some(2)),
+            ("src", "directory", 1, None),
+            ("src/main.rs", "file", 2, Some(12)),
+        ]
+    );
+    assert!(lines[0]["mtime"].as_str().unwrap().ends_with('Z'));
+    assert!(lines[1]["mtime"].is_null());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
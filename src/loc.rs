@@ -0,0 +1,72 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+};
+
+use crate::{Directory, FileKind, TreeEntry};
+
+/// Counts lines for `--loc`, remembering each file so directory totals and the file's own
+/// annotation read it only once.
+#[derive(Debug, Default)]
+pub struct LineCounter {
+    counted: RefCell<HashMap<PathBuf, Option<u64>>>,
+}
+
+impl LineCounter {
+    /// `None` for binary or unreadable files.
+    pub fn file(&self, path: &Path) -> Option<u64> {
+        if let Some(&lines) = self.counted.borrow().get(path) {
+            return lines;
+        }
+        let lines = count_lines(fs::File::open(path).ok()?).ok().flatten();
+        self.counted.borrow_mut().insert(path.to_path_buf(), lines);
+        lines
+    }
+
+    /// The lines of every text file below `dir`.
+    pub fn dir(&self, dir: &Directory) -> u64 {
+        dir.subdirectories
+            .iter()
+            .map(|entry| match entry {
+                TreeEntry::FileNode(file) if file.kind == FileKind::Regular => {
+                    self.file(&file.path).unwrap_or(0)
+                }
+                TreeEntry::DirNode(child) => self.dir(child),
+                TreeEntry::FileNode(_) | TreeEntry::SymlinkNode(_) => 0,
+            })
+            .sum()
+    }
+}
+
+//newlines, plus a last line that doesn't end in one; `None` once a NUL byte shows it's binary
+fn count_lines(mut reader: impl Read) -> io::Result<Option<u64>> {
+    let mut buf = [0u8; 64 * 1024];
+    let mut lines = 0;
+    let mut last = b'\n';
+    loop {
+        let read = reader.read(&mut buf)?;
+        if read == 0 {
+            break;
+        }
+        let chunk = &buf[..read];
+        if chunk.contains(&0) {
+            return Ok(None);
+        }
+        lines += chunk.iter().filter(|&&byte| byte == b'\n').count() as u64;
+        last = chunk[read - 1];
+    }
+    Ok(Some(lines + u64::from(last != b'\n')))
+}
+
+#[test]
+fn count_text_and_skip_binary() {
+    let count = |bytes: &[u8]| count_lines(bytes).unwrap();
+    assert_eq!(count(b""), Some(0));
+    assert_eq!(count(b"one\ntwo\n"), Some(2));
+    assert_eq!(count(b"one\ntwo"), Some(2));
+    assert_eq!(count(b"\n\n\n"), Some(3));
+    assert_eq!(count(b"ELF\0\x01"), None);
+}
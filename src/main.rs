@@ -1,10 +1,65 @@
 //tree [path?]
 
-use std::{fs, os::windows::fs::MetadataExt, path::PathBuf, str::FromStr};
+#[cfg(feature = "zip")]
+mod archive;
+mod by_ext;
+mod cli;
+mod color;
+mod config;
+mod date;
+mod diff;
+mod duplicates;
+#[cfg(any(feature = "json", feature = "yaml"))]
+mod export;
+mod filetype;
+mod flat;
+mod glob;
+mod hash;
+#[cfg(feature = "json")]
+mod jsonl;
+mod loc;
+mod markdown;
+mod normalize;
+mod pager;
+mod partial;
+mod platform;
+mod portability;
+mod skeleton;
+mod sort;
+mod style;
+mod summary;
+mod top;
+mod tsv;
+#[cfg(feature = "json")]
+mod verify;
+
+#[cfg(windows)]
+use std::os::windows::fs::MetadataExt;
+use std::{
+    cell::Cell,
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::{Instant, SystemTime},
+};
 
 use anyhow::{Context, Result};
+use color::{AgeColors, Color};
+use config::{Config, CONFIG_FILE};
+use date::{DateFormat, TimeType};
+use hash::HashAlgorithm;
+use pager::Output;
+use platform::FilesystemId;
+use style::{Encoding, RenderStyle};
+use summary::Summary;
+
+const PIPE: &str = "\u{2502}\u{00A0}\u{00A0}"; // │
+const TEE_PIPE: &str = "\u{251c}\u{2500}\u{2500} "; // ├──
+const SPACES: &str = "\u{00A0}\u{00A0} "; // └─
+const L_PIPE: &str = "\u{2514}\u{2500} "; // └─
 
 #[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
 enum TreeEntry {
     DirNode(Directory),
     FileNode(File),
@@ -14,164 +69,2783 @@ enum TreeEntry {
 #[derive(Debug)]
 struct File {
     name: String,
+    path: PathBuf,
+    //from the metadata, or from the archive when reading a zip
+    size: Option<u64>,
     metadata: Option<fs::Metadata>,
+    kind: FileKind,
+}
+
+/// What a `File` is. Everything but `Regular` only exists on Unix, and those are never opened,
+/// since reading a FIFO blocks until something writes to it.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(not(unix), allow(dead_code))]
+enum FileKind {
+    #[default]
+    Regular,
+    Fifo,
+    Socket,
+    BlockDevice,
+    CharDevice,
+}
+
+impl FileKind {
+    fn label(self) -> Option<&'static str> {
+        match self {
+            FileKind::Regular => None,
+            FileKind::Fifo => Some("fifo"),
+            FileKind::Socket => Some("socket"),
+            FileKind::BlockDevice => Some("block device"),
+            FileKind::CharDevice => Some("char device"),
+        }
+    }
+
+    //the -F suffix, as `ls -F` writes it
+    fn marker(self) -> &'static str {
+        match self {
+            FileKind::Fifo => "|",
+            FileKind::Socket => "=",
+            _ => "",
+        }
+    }
 }
 
 #[derive(Debug)]
 struct Directory {
     name: String,
     subdirectories: Vec<TreeEntry>,
+    //why the directory couldn't be read; it's kept in the tree with no entries
+    error: Option<String>,
+    //the subtree's size when --skip-dir-over dropped its entries
+    skipped: Option<u64>,
 }
 
 #[derive(Debug)]
 struct Symlink {
     name: String,
+    //the link itself, like `File::path`
+    path: PathBuf,
     target: String,
-    metadata: Option<fs::Metadata>,
+    //the link itself and whatever it points at; the target's is None for a broken link
+    link_metadata: Option<fs::Metadata>,
+    target_metadata: Option<fs::Metadata>,
+}
+
+/// Which metadata `--link-info` shows for a symlink.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+enum LinkInfo {
+    #[default]
+    Link,
+    Target,
+}
+
+impl Symlink {
+    fn metadata(&self, info: LinkInfo) -> Option<&fs::Metadata> {
+        match info {
+            LinkInfo::Link => self.link_metadata.as_ref(),
+            LinkInfo::Target => self.target_metadata.as_ref(),
+        }
+    }
+
+    /// Whether the link points at a directory it is itself inside of, so a walk that followed it
+    /// would go round in circles. Symlinks aren't followed, so this is only pointed out.
+    fn points_to_ancestor(&self) -> bool {
+        let Ok(target) = fs::canonicalize(&self.path) else {
+            return false;
+        };
+        let parent = self
+            .path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        target.is_dir() && fs::canonicalize(parent).is_ok_and(|parent| parent.starts_with(&target))
+    }
+
+    /// `--resolve-links`: the target as an absolute path, with relative targets taken from the
+    /// link's directory. Canonical when the target exists; a broken link's target is only made
+    /// absolute. `None` when the chain of links is longer than `max_resolutions`.
+    fn resolved_target(&self, max_resolutions: usize) -> Option<PathBuf> {
+        let target = follow_links(&self.path, max_resolutions)?;
+        let resolved = fs::canonicalize(&target)
+            .or_else(|_| std::path::absolute(&target))
+            .unwrap_or(target);
+        Some(platform::short_path(&resolved))
+    }
+}
+
+/// `--max-symlink-resolutions`' default, the limit Linux puts on a single path lookup.
+const MAX_SYMLINK_RESOLUTIONS: usize = 40;
+
+//follows `path` for as long as it is a symlink, each target taken from its link's directory.
+//`None` when that takes more than `max` hops, like the ELOOP the OS would give, so a loop
+//of links ends too
+fn follow_links(path: &Path, max: usize) -> Option<PathBuf> {
+    let mut current = path.to_path_buf();
+    for _ in 0..=max {
+        let Ok(target) = fs::read_link(&current) else {
+            return Some(current);
+        };
+        current = match current.parent() {
+            Some(parent) => parent.join(target),
+            None => target,
+        };
+    }
+    None
+}
+
+/// Settings that decide which entries the walk keeps.
+#[derive(Debug, Default, Clone)]
+struct WalkOptions {
+    excludes: Vec<String>,
+    //--ext: only files and symlinks with one of these lowercase extensions are kept
+    extensions: Vec<String>,
+    //--no-extension: files and symlinks without any extension are kept too, or only them
+    //without --ext
+    extensionless: bool,
+    //-a lists hidden entries; --descend-hidden walks into hidden directories (and so lists them).
+    //a hidden directory that is listed but not walked shows up empty
+    show_hidden: bool,
+    recurse_hidden_dirs: bool,
+    //--names-only: no metadata is read, so sizes and times are unknown
+    names_only: bool,
+    //--disk-usage: file sizes are the space taken on disk rather than the length
+    disk_usage: bool,
+    //set by --same-fs to the root's filesystem; directories elsewhere are listed but not entered
+    stay_on_filesystem: Option<FilesystemId>,
+    //--bundles opaque: macOS bundles are listed with their size but not entered
+    opaque_bundles: bool,
+    //--timeout: directories reached after this are listed but not read
+    deadline: Option<Instant>,
+    timed_out: Cell<bool>,
+    //hidden entries left out, for --report-hidden
+    hidden_skipped: Cell<usize>,
+    //--max-entries: once this many entries are kept, the walk stops adding any
+    max_entries: Option<usize>,
+    entries_kept: Cell<usize>,
+    limit_reached: Cell<bool>,
+    //--compare-names-unicode-normalized: excludes match names in NFC
+    normalize_names: bool,
+}
+
+impl WalkOptions {
+    //a pattern ending in '/' only excludes directories
+    fn is_excluded(&self, name: &str, is_dir: bool) -> bool {
+        let normalize = self.normalize_names;
+        self.excludes
+            .iter()
+            .any(|pattern| match pattern.strip_suffix('/') {
+                Some(dir_pattern) => is_dir && normalize::glob_match(dir_pattern, name, normalize),
+                None => normalize::glob_match(pattern, name, normalize),
+            })
+    }
+
+    fn is_filtered_by_extension(&self, name: &str) -> bool {
+        if self.extensions.is_empty() && !self.extensionless {
+            return false;
+        }
+        //like `FileEntry::get_extension`, so `.bashrc` has none
+        match Path::new(name).extension() {
+            Some(extension) => !self
+                .extensions
+                .contains(&extension.to_string_lossy().to_lowercase()),
+            None => !self.extensionless,
+        }
+    }
+
+    fn count_hidden(&self) {
+        self.hidden_skipped.set(self.hidden_skipped.get() + 1);
+    }
+
+    //counts one more kept entry, or returns false when --max-entries are already kept
+    fn take_entry(&self) -> bool {
+        let Some(max_entries) = self.max_entries else {
+            return true;
+        };
+        if self.entries_kept.get() >= max_entries {
+            self.limit_reached.set(true);
+            return false;
+        }
+        self.entries_kept.set(self.entries_kept.get() + 1);
+        true
+    }
+
+    fn past_deadline(&self) -> bool {
+        let past = self
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline);
+        if past {
+            self.timed_out.set(true);
+        }
+        past
+    }
+
+    fn file_size(&self, metadata: &fs::Metadata, path: &Path) -> u64 {
+        if self.disk_usage {
+            platform::disk_usage(metadata, path)
+        } else {
+            metadata.len()
+        }
+    }
+
+    fn metadata(&self, read: impl FnOnce() -> io::Result<fs::Metadata>) -> Option<fs::Metadata> {
+        if self.names_only {
+            None
+        } else {
+            read().ok()
+        }
+    }
+}
+
+/// The kinds of annotation shown after a name, from the most important to the least. Past
+/// `--width`, whole columns are left out starting from the last one; what a `Status` says about
+/// the entry itself, like an error, is always kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Column {
+    Status,
+    ShortName,
+    Size,
+    Date,
+    Created,
+    Type,
+    #[cfg(unix)]
+    Sparse,
+    Lines,
+    Hash,
+}
+
+/// Settings that only change how the walked tree is printed.
+#[derive(Debug, Default)]
+struct DisplayOptions {
+    age_colors: Option<AgeColors>,
+    hash: Option<HashAlgorithm>,
+    highlights: Vec<String>,
+    //--compare-names-unicode-normalized: highlights match names in NFC
+    normalize_names: bool,
+    date_format: Option<DateFormat>,
+    link_info: LinkInfo,
+    encoding: Encoding,
+    //the encoding's connectors, or the --tree-chars ones
+    style: RenderStyle,
+    per_dir_limit: Option<usize>,
+    //--sample: files shown per extension in each directory
+    sample: Option<usize>,
+    //--reverse-depth: entries above their directory, the root last
+    reverse_depth: bool,
+    colorize: bool,
+    //--no-ext; matching and other output formats keep the full name
+    strip_extensions: bool,
+    //replaces the root path in the header; the walk still uses the real path
+    root_label: Option<String>,
+    time_type: TimeType,
+    //--created: the birth time next to the --date one
+    created: bool,
+    quote_names: bool,
+    //-F: `/` after directories, `*` after programs and `@` after symlinks
+    classify: bool,
+    detect_type: bool,
+    loc: Option<loc::LineCounter>,
+    depth_histogram: bool,
+    opaque_bundles: bool,
+    //--size-if-over: only files bigger than this show their size
+    size_if_over: Option<u64>,
+    //decimals of the human-readable sizes, when not Size's default
+    size_precision: Option<usize>,
+    resolve_links: bool,
+    //links followed before a target counts as unresolvable
+    max_symlink_resolutions: Option<usize>,
+    //--sparse, Unix only
+    sparse: bool,
+    //--short-names, Windows only
+    short_names: bool,
+    //the walk hit --timeout, so the summary only counts what was read
+    incomplete: bool,
+    //--on-limit truncate: the walk stopped at this many --max-entries
+    truncated_at: Option<usize>,
+    //--report-hidden: how many hidden entries the walk left out
+    hidden_skipped: Option<usize>,
+    //--age-summary: the oldest and newest file times after the counts
+    age_summary: bool,
+    //--collapse-after: directories deeper than this are shown as one summary line
+    collapse_after: Option<usize>,
+    //--width, or the terminal's: annotations are left out so lines fit in this many columns
+    width: Option<usize>,
+}
+
+impl DisplayOptions {
+    //whether a directory listed at `depth` is summarized instead of expanded
+    fn is_collapsed(&self, depth: usize) -> bool {
+        self.collapse_after.is_some_and(|n| depth > n)
+    }
+
+    //`, oldest: 2019-03-01, newest: 2024-06-12`, dates only unless a --date-format is given
+    fn age_range(&self, tree: &Directory) -> String {
+        if !self.age_summary {
+            return String::new();
+        }
+        let format = match &self.date_format {
+            Some(format) => format.clone(),
+            None => "%F".parse().expect("date-only format is valid"),
+        };
+        match Summary::with_times(tree, self.time_type).age_range(&format) {
+            Some(range) => format!(", {range}"),
+            None => String::new(),
+        }
+    }
+
+    fn paint(&self, text: &str, color: Color) -> String {
+        if self.colorize {
+            color::paint(text, color)
+        } else {
+            text.to_string()
+        }
+    }
+
+    fn file_name(&self, file: &File, now: SystemTime) -> String {
+        let name = match Path::new(&file.name).file_stem() {
+            Some(stem) if self.strip_extensions => stem.to_string_lossy(),
+            _ => file.name.as_str().into(),
+        };
+        let name = self.quote(&name);
+        let executable = file
+            .metadata
+            .as_ref()
+            .is_some_and(|metadata| platform::is_executable(metadata, &file.path));
+        let suffix = match file.kind {
+            _ if !self.classify => "",
+            FileKind::Regular if executable => "*",
+            kind => kind.marker(),
+        };
+        if self.is_highlighted(&file.name) {
+            return self.paint(&name, Color::Highlight) + suffix;
+        }
+        let color = self
+            .age_colors
+            .and_then(|age_colors| {
+                let modified = self.time_type.of(file.metadata.as_ref()?)?;
+                age_colors.color_for(modified, now)
+            })
+            .or(executable.then_some(Color::Executable));
+        match color {
+            Some(color) => self.paint(&name, color) + suffix,
+            None => name + suffix,
+        }
+    }
+
+    fn dir_name(&self, dir: &Directory) -> String {
+        let name = self.quote(&dir.name);
+        let suffix = if self.classify { "/" } else { "" };
+        if self.is_highlighted(&dir.name) {
+            self.paint(&name, Color::Highlight) + suffix
+        } else {
+            name + suffix
+        }
+    }
+
+    fn size(&self, bytes: u64) -> String {
+        match self.size_precision {
+            Some(precision) => format!("{:.*}", precision, summary::Size(bytes)),
+            None => summary::Size(bytes).to_string(),
+        }
+    }
+
+    fn link_target(&self, link: &Symlink) -> String {
+        match self.resolved_target(link) {
+            Some(Some(resolved)) => self.quote(&display_path(&resolved)),
+            //a chain too long to resolve keeps its target as written
+            Some(None) | None => self.quote(&link.target),
+        }
+    }
+
+    //`None` without --resolve-links
+    fn resolved_target(&self, link: &Symlink) -> Option<Option<PathBuf>> {
+        self.resolve_links.then(|| {
+            link.resolved_target(
+                self.max_symlink_resolutions
+                    .unwrap_or(MAX_SYMLINK_RESOLUTIONS),
+            )
+        })
+    }
+
+    fn link_name(&self, link: &Symlink) -> String {
+        let name = self.quote(&link.name);
+        if self.classify {
+            name + "@"
+        } else {
+            name
+        }
+    }
+
+    fn quote(&self, name: &str) -> String {
+        if self.quote_names {
+            quoted(name)
+        } else {
+            name.to_string()
+        }
+    }
+
+    fn is_highlighted(&self, name: &str) -> bool {
+        self.highlights
+            .iter()
+            .any(|pattern| normalize::glob_match(pattern, name, self.normalize_names))
+    }
+
+    //the bracketed extras printed after a file's name
+    //`used`: how wide the line is before the annotations, for --width
+    fn file_annotations(&self, file: &File, used: usize) -> String {
+        let mut annotations = self.short_name(&file.path);
+        if let Some(size) = file
+            .size
+            .filter(|&size| self.size_if_over.is_some_and(|limit| size > limit))
+        {
+            annotations.push((Column::Size, self.size(size)));
+        }
+        annotations.extend(self.metadata_annotations(file.metadata.as_ref()));
+        if let Some(label) = file.kind.label() {
+            annotations.push((Column::Status, label.to_string()));
+            //there are no contents to read
+            return self.fitted(annotations, used);
+        }
+        if self.detect_type {
+            annotations.push((
+                Column::Type,
+                filetype::detect(&file.path).unwrap_or("-").to_string(),
+            ));
+        }
+        #[cfg(unix)]
+        if let Some(metadata) = file.metadata.as_ref().filter(|_| self.sparse) {
+            let allocated = platform::allocated_bytes(metadata);
+            //less than half allocated, and by a margin that small files' block rounding or
+            //inlining can't explain
+            if allocated < metadata.len() / 2 && metadata.len() - allocated >= 64 * 1024 {
+                annotations.push((Column::Sparse, "sparse".to_string()));
+                annotations.push((
+                    Column::Sparse,
+                    format!("{} apparent", self.size(metadata.len())),
+                ));
+                annotations.push((
+                    Column::Sparse,
+                    format!("{} allocated", self.size(allocated)),
+                ));
+            }
+        }
+        if let Some(lines) = self.loc.as_ref().and_then(|loc| loc.file(&file.path)) {
+            annotations.push((Column::Lines, format!("{lines} lines")));
+        }
+        if let Some(algorithm) = self.hash {
+            annotations.push((
+                Column::Hash,
+                hash::file_digest(&file.path, algorithm).unwrap_or_else(|_| "-".to_string()),
+            ));
+        }
+        self.fitted(annotations, used)
+    }
+
+    //`collapsed` by --collapse-after: the file count and size of everything below it come first
+    fn dir_annotations(
+        &self,
+        dir: &Directory,
+        path: &Path,
+        collapsed: bool,
+        used: usize,
+    ) -> String {
+        let mut annotations = self.short_name(path);
+        if collapsed {
+            let totals = Summary::of(dir);
+            annotations.push((
+                Column::Size,
+                format!("{} files", thousands(totals.files + totals.symlinks)),
+            ));
+            annotations.push((Column::Size, self.size(totals.bytes)));
+        }
+        if let Some(loc) = &self.loc {
+            annotations.push((Column::Lines, format!("{} lines", loc.dir(dir))));
+        }
+        if let Some(err) = &dir.error {
+            annotations.push((Column::Status, format!("error: {err}")));
+        }
+        if let Some(bytes) = dir.skipped {
+            let label = if self.opaque_bundles && is_bundle(&dir.name) {
+                "bundle"
+            } else {
+                "skipped"
+            };
+            annotations.extend([
+                (Column::Status, label.to_string()),
+                (Column::Status, self.size(bytes)),
+            ]);
+        }
+        self.fitted(annotations, used)
+    }
+
+    fn symlink_annotations(&self, link: &Symlink, used: usize) -> String {
+        let mut annotations = self.short_name(&link.path);
+        annotations.extend(self.metadata_annotations(link.metadata(self.link_info)));
+        if let Some(None) = self.resolved_target(link) {
+            annotations.push((Column::Status, "too many levels of symlinks".to_string()));
+        }
+        if link.points_to_ancestor() {
+            annotations.push((Column::Status, "cycle".to_string()));
+        }
+        self.fitted(annotations, used)
+    }
+
+    //--width: while the line is too wide, the least important column is left out, all of it
+    fn fitted(&self, mut annotations: Vec<(Column, String)>, used: usize) -> String {
+        let text = |annotations: &[(Column, String)]| {
+            bracketed(annotations.iter().map(|(_, text)| text.clone()).collect())
+        };
+        if let Some(width) = self.width {
+            while used + display_width(&text(&annotations)) > width {
+                let Some(least) = annotations
+                    .iter()
+                    .map(|(column, _)| *column)
+                    .filter(|column| *column != Column::Status)
+                    .max()
+                else {
+                    break;
+                };
+                annotations.retain(|(column, _)| *column != least);
+            }
+        }
+        text(&annotations)
+    }
+
+    //--short-names, Windows only: `short PROGRA~1` when there is an 8.3 name unlike the long one
+    fn short_name(&self, path: &Path) -> Vec<(Column, String)> {
+        if !self.short_names {
+            return Vec::new();
+        }
+        platform::short_name(path)
+            .map(|short| (Column::ShortName, format!("short {short}")))
+            .into_iter()
+            .collect()
+    }
+
+    fn metadata_annotations(&self, metadata: Option<&fs::Metadata>) -> Vec<(Column, String)> {
+        let mut annotations = Vec::new();
+        if let Some(date_format) = &self.date_format {
+            annotations.push((
+                Column::Date,
+                match metadata.and_then(|metadata| self.time_type.of(metadata)) {
+                    Some(modified) => date_format.format(modified),
+                    None => "-".to_string(),
+                },
+            ));
+        }
+        if self.created {
+            let format = self.date_format.clone().unwrap_or_default();
+            annotations.push((
+                Column::Created,
+                match metadata.and_then(|metadata| metadata.created().ok()) {
+                    Some(created) => format!("created {}", format.format(created)),
+                    None => "created -".to_string(),
+                },
+            ));
+        }
+        annotations
+    }
+}
+
+/// `--quote-names`: wraps a name in double quotes, escaping what would end or break the quoting
+/// the way `ls -Q` does.
+fn quoted(name: &str) -> String {
+    let mut quoted = String::with_capacity(name.len() + 2);
+    quoted.push('"');
+    for c in name.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            '\r' => quoted.push_str("\\r"),
+            c if c.is_control() => quoted.push_str(&format!("\\x{:02x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+//how many columns `text` takes up on a terminal, not counting color codes
+fn display_width(text: &str) -> usize {
+    let mut width = 0;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            //up to and including the letter that ends the escape sequence
+            chars.by_ref().find(char::is_ascii_alphabetic);
+        } else {
+            width += 1;
+        }
+    }
+    width
+}
+
+fn bracketed(annotations: Vec<String>) -> String {
+    if annotations.is_empty() {
+        String::new()
+    } else {
+        format!(" [{}]", annotations.join(", "))
+    }
+}
+
+//`1,204`: a count with commas between each group of three digits
+fn thousands(n: usize) -> String {
+    let digits = n.to_string();
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+impl Directory {
+    //the subdirectory called `name`, created empty when there isn't one yet; for trees
+    //that aren't built from a directory listing
+    fn child_dir(&mut self, name: &str) -> &mut Directory {
+        let position = self
+            .subdirectories
+            .iter()
+            .position(|entry| matches!(entry, TreeEntry::DirNode(child) if child.name == name));
+        let position = position.unwrap_or_else(|| {
+            self.subdirectories.push(TreeEntry::DirNode(Directory {
+                name: name.to_string(),
+                subdirectories: Vec::new(),
+                error: None,
+                skipped: None,
+            }));
+            self.subdirectories.len() - 1
+        });
+        match &mut self.subdirectories[position] {
+            TreeEntry::DirNode(child) => child,
+            _ => unreachable!(),
+        }
+    }
+
+    //--empty: keeps only zero-byte files, directories that had no entries to begin with and
+    //the directories leading to them. Unreadable and skipped directories aren't empty
+    fn retain_empty(&mut self) {
+        self.subdirectories.retain_mut(|entry| match entry {
+            TreeEntry::FileNode(file) => file.size == Some(0),
+            TreeEntry::SymlinkNode(_) => false,
+            TreeEntry::DirNode(dir) if dir.subdirectories.is_empty() => {
+                dir.error.is_none() && dir.skipped.is_none()
+            }
+            TreeEntry::DirNode(dir) => {
+                dir.retain_empty();
+                !dir.subdirectories.is_empty()
+            }
+        });
+    }
+
+    //--dirs-with-match: keeps only the directories holding a file or symlink whose name matches
+    //`pattern` and the ones leading to them, with no files at all. Returns whether `self` holds one.
+    //`normalize` matches names in NFC
+    fn retain_dirs_with_match(&mut self, pattern: &str, normalize: bool) -> bool {
+        let holds_match = self.subdirectories.iter().any(|entry| {
+            !matches!(entry, TreeEntry::DirNode(_))
+                && normalize::glob_match(pattern, entry.name(), normalize)
+        });
+        self.subdirectories.retain_mut(|entry| match entry {
+            TreeEntry::DirNode(dir) => {
+                dir.retain_dirs_with_match(pattern, normalize) || !dir.subdirectories.is_empty()
+            }
+            _ => false,
+        });
+        holds_match
+    }
+
+    //--prune: drops the directories without a file or symlink anywhere below them. Unreadable
+    //and skipped directories stay, as what they hold isn't known. Returns whether anything is left
+    fn prune(&mut self) -> bool {
+        self.subdirectories.retain_mut(|entry| match entry {
+            TreeEntry::DirNode(dir) => dir.prune() || dir.error.is_some() || dir.skipped.is_some(),
+            _ => true,
+        });
+        !self.subdirectories.is_empty()
+    }
+
+    //--regular-only: drops FIFOs, sockets and devices everywhere below
+    fn retain_regular(&mut self) {
+        self.subdirectories.retain_mut(|entry| match entry {
+            TreeEntry::FileNode(file) => file.kind == FileKind::Regular,
+            TreeEntry::SymlinkNode(_) => true,
+            TreeEntry::DirNode(dir) => {
+                dir.retain_regular();
+                true
+            }
+        });
+    }
+
+    //--posix-paths: the root's name and symlink targets are the only paths that keep the
+    //platform's separators, entry paths are always built with `/`
+    fn use_posix_separators(&mut self) {
+        self.name = posix_path(&self.name);
+        for entry in self.subdirectories.iter_mut() {
+            match entry {
+                TreeEntry::DirNode(dir) => dir.use_posix_separators(),
+                TreeEntry::SymlinkNode(link) => link.target = posix_path(&link.target),
+                TreeEntry::FileNode(_) => (),
+            }
+        }
+    }
+}
+
+impl TreeEntry {
+    fn name(&self) -> &str {
+        match self {
+            TreeEntry::DirNode(dir) => &dir.name,
+            TreeEntry::FileNode(file) => &file.name,
+            TreeEntry::SymlinkNode(link) => &link.name,
+        }
+    }
+}
+
+//--sample: the first `n` files of each extension and every other entry, in order, with how many
+//files of each extension were left out, in the order the extensions first appear
+fn sample_entries(
+    entries: &[TreeEntry],
+    n: usize,
+) -> (Vec<&TreeEntry>, Vec<(Option<String>, usize)>) {
+    let mut kept = Vec::new();
+    let mut seen: Vec<(Option<String>, usize)> = Vec::new();
+    for entry in entries {
+        let TreeEntry::FileNode(file) = entry else {
+            kept.push(entry);
+            continue;
+        };
+        let extension = Path::new(&file.name)
+            .extension()
+            .map(|ext| ext.to_string_lossy().into_owned());
+        let i = match seen.iter().position(|(ext, _)| *ext == extension) {
+            Some(i) => i,
+            None => {
+                seen.push((extension, 0));
+                seen.len() - 1
+            }
+        };
+        seen[i].1 += 1;
+        if seen[i].1 <= n {
+            kept.push(entry);
+        }
+    }
+    let left_out = seen
+        .into_iter()
+        .filter(|(_, count)| *count > n)
+        .map(|(ext, count)| (ext, count - n))
+        .collect();
+    (kept, left_out)
+}
+
+//the macOS packages that `--bundles opaque` keeps closed
+fn is_bundle(name: &str) -> bool {
+    Path::new(name).extension().is_some_and(|ext| {
+        ["app", "bundle", "framework"]
+            .iter()
+            .any(|bundle| ext.eq_ignore_ascii_case(bundle))
+    })
+}
+
+//the sum of the file sizes below `path`, without building a tree or following links
+fn dir_bytes(path: &Path, options: &WalkOptions) -> u64 {
+    let Ok(entries) = fs::read_dir(platform::long_path(path)) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| match entry.file_type() {
+            Ok(file_type) if file_type.is_dir() => dir_bytes(&entry.path(), options),
+            Ok(file_type) if file_type.is_file() => entry
+                .metadata()
+                .map_or(0, |metadata| options.file_size(&metadata, &entry.path())),
+            _ => 0,
+        })
+        .sum()
+}
+
+//with `names_only` the Windows hidden attribute isn't read and only the leading '.' counts
+fn is_hidden(entry: &fs::DirEntry, names_only: bool) -> bool {
+    if entry.file_name().to_str().unwrap().starts_with('.') {
+        return true;
+    }
+    if names_only {
+        return false;
+    }
+    #[cfg(windows)]
+    if let Ok(metadata) = entry.metadata() {
+        const FILE_ATTRIBUTE_HIDDEN: u32 = 0x02;
+        let file_attr = metadata.file_attributes();
+
+        //FILE_ATTRIBUTE_HIDDEN is 0x02 for windows and
+        //any number that results in a number greater than zero after bitwise-and with it is hidden
+        return file_attr & FILE_ATTRIBUTE_HIDDEN != 0;
+    }
+    false
+}
+
+//a `.zip` root is read from the archive instead of the filesystem
+fn read_tree(path: &PathBuf, options: &WalkOptions) -> Result<Directory> {
+    if path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("zip"))
+        && path.is_file()
+    {
+        #[cfg(feature = "zip")]
+        return archive::read_zip(path, options);
+        #[cfg(not(feature = "zip"))]
+        anyhow::bail!(
+            "reading {} needs groot built with the zip feature",
+            path.display()
+        );
+    }
+    walk_dir(path, options)
 }
 
-fn walk_dir(path: &PathBuf) -> Result<Directory> {
-    let dir_iter = std::fs::read_dir(path).context(format!("unable to read dir: {path:#?}"))?;
+fn walk_dir(path: &PathBuf, options: &WalkOptions) -> Result<Directory> {
+    list_dir(path, options, &mut |sub_dir| walk_dir(sub_dir, options))
+}
+
+/// Reads one directory's entries, handing every subdirectory that the walk goes into
+/// to `descend` for its contents.
+fn list_dir(
+    path: &PathBuf,
+    options: &WalkOptions,
+    descend: &mut dyn FnMut(&PathBuf) -> Result<Directory>,
+) -> Result<Directory> {
+    //entry paths below inherit the long-path form, so the whole walk can go past MAX_PATH
+    let dir_iter = std::fs::read_dir(platform::long_path(path))
+        .context(format!("unable to read dir: {path:#?}"))?;
 
     let mut sub_dirs: Vec<TreeEntry> = Vec::new();
 
     for dir_entry in dir_iter {
-        let node: TreeEntry;
-        match dir_entry {
+        let node = match dir_entry {
             Ok(entry) => {
                 // println!("entry=> {entry:#?}");
 
-                node = match entry {
-                    file_entry if entry.path().is_file() => {
-                        //do file things
-                        if file_entry.file_name().to_str().unwrap().starts_with(".") {
+                //usually known from the directory listing itself, without a stat
+                let file_type = match entry.file_type() {
+                    Ok(file_type) => file_type,
+                    Err(_) => continue,
+                };
+                match entry {
+                    //checked first since is_file and is_dir follow the link
+                    sym_entry if file_type.is_symlink() => {
+                        let name = sym_entry.file_name();
+                        let name = name.to_str().unwrap();
+                        if options.is_excluded(name, false)
+                            || options.is_filtered_by_extension(name)
+                        {
                             continue;
                         }
-                        if let Ok(metadata) = file_entry.metadata() {
-                            const FILE_ATTRIBUTE_HIDDEN: u32 = 0x02;
-                            let file_attr = metadata.file_attributes();
-
-                            //FILE_ATTRIBUTE_HIDDEN is 0x02 for windows and
-                            //any number that results in a number greater than zero after bitwise-and with it is hidden
-                            if file_attr & FILE_ATTRIBUTE_HIDDEN != 0 {
-                                //file is hidden
-
-                                continue;
-                            }
+                        if is_hidden(&sym_entry, options.names_only) && !options.show_hidden {
+                            options.count_hidden();
+                            continue;
+                        }
+                        if !options.take_entry() {
+                            break;
                         }
-                        TreeEntry::FileNode(File {
-                            name: file_entry.file_name().to_str().unwrap().to_string(),
-                            metadata: file_entry.metadata().ok(),
-                        })
-                    }
-                    sym_entry if entry.path().is_symlink() => {
                         //proceed with symbolic linky things
                         TreeEntry::SymlinkNode(Symlink {
                             name: sym_entry.file_name().to_str().unwrap().to_string(),
+                            path: sym_entry.path(),
                             target: fs::read_link(sym_entry.path())?.to_string_lossy().into(),
-                            metadata: sym_entry.metadata().ok(),
+                            link_metadata: options
+                                .metadata(|| fs::symlink_metadata(sym_entry.path())),
+                            target_metadata: options.metadata(|| fs::metadata(sym_entry.path())),
                         })
                     }
-                    dir_entry if entry.path().is_dir() => {
+                    //FIFOs, sockets and devices too, which only exist on Unix
+                    file_entry if !file_type.is_dir() => {
                         //do file things
-                        if dir_entry.file_name().to_str().unwrap().starts_with(".") {
-                            // println!(
-                            //     "skipping dir cuz startswith '.'=> {}",
-                            //     dir_entry.file_name().to_str().unwrap()
-                            // );
+                        let name = file_entry.file_name();
+                        let name = name.to_str().unwrap();
+                        if options.is_excluded(name, false)
+                            || options.is_filtered_by_extension(name)
+                        {
                             continue;
                         }
-                        if let Ok(metadata) = dir_entry.metadata() {
-                            const FILE_ATTRIBUTE_HIDDEN: u32 = 0x02;
-                            let file_attr = metadata.file_attributes();
-
-                            //FILE_ATTRIBUTE_HIDDEN is 0x02 for windows and
-                            //any number that results in a number greater than zero after bitwise-and with it is hidden
-                            if file_attr & FILE_ATTRIBUTE_HIDDEN != 0 {
-                                //file is hidden
-
-                                continue;
-                            }
+                        if is_hidden(&file_entry, options.names_only) && !options.show_hidden {
+                            options.count_hidden();
+                            continue;
+                        }
+                        if !options.take_entry() {
+                            break;
+                        }
+                        let metadata = options.metadata(|| file_entry.metadata());
+                        TreeEntry::FileNode(File {
+                            name: file_entry.file_name().to_str().unwrap().to_string(),
+                            path: file_entry.path(),
+                            size: metadata
+                                .as_ref()
+                                .map(|metadata| options.file_size(metadata, &file_entry.path())),
+                            metadata,
+                            kind: platform::file_kind(&file_type),
+                        })
+                    }
+                    dir_entry => {
+                        //do file things
+                        let hidden = is_hidden(&dir_entry, options.names_only);
+                        if options.is_excluded(dir_entry.file_name().to_str().unwrap(), true) {
+                            continue;
+                        }
+                        if hidden && !options.show_hidden && !options.recurse_hidden_dirs {
+                            options.count_hidden();
+                            continue;
+                        }
+                        //taken before walking it, so its entries are the ones left out
+                        if !options.take_entry() {
+                            break;
+                        }
+                        if (hidden && !options.recurse_hidden_dirs)
+                            || options.stay_on_filesystem.is_some()
+                                && platform::filesystem_id(&dir_entry.path())
+                                    != options.stay_on_filesystem
+                        {
+                            TreeEntry::DirNode(Directory {
+                                name: dir_entry.file_name().to_str().unwrap().to_string(),
+                                subdirectories: Vec::new(),
+                                error: None,
+                                skipped: None,
+                            })
+                        } else if options.opaque_bundles
+                            && is_bundle(dir_entry.file_name().to_str().unwrap())
+                        {
+                            TreeEntry::DirNode(Directory {
+                                name: dir_entry.file_name().to_str().unwrap().to_string(),
+                                subdirectories: Vec::new(),
+                                error: None,
+                                skipped: (!options.names_only)
+                                    .then(|| dir_bytes(&dir_entry.path(), options)),
+                            })
+                        } else if options.past_deadline() {
+                            TreeEntry::DirNode(Directory {
+                                name: dir_entry.file_name().to_str().unwrap().to_string(),
+                                subdirectories: Vec::new(),
+                                error: Some("walk timed out".to_string()),
+                                skipped: None,
+                            })
+                        } else {
+                            //proceed with directory recursion
+                            TreeEntry::DirNode(descend(&dir_entry.path()).unwrap_or_else(|err| {
+                                Directory {
+                                    name: dir_entry.file_name().to_str().unwrap().to_string(),
+                                    subdirectories: Vec::new(),
+                                    error: Some(err.root_cause().to_string()),
+                                    skipped: None,
+                                }
+                            }))
                         }
-                        //proceed with directory recursion
-                        TreeEntry::DirNode(walk_dir(&dir_entry.path())?)
                     }
-
-                    _ => unreachable!(),
                 }
             }
             Err(_) => {
                 continue;
             }
-        }
+        };
         sub_dirs.push(node);
     }
 
     Ok(Directory {
-        name: path.file_name().unwrap().to_str().unwrap().into(),
+        name: root_name(path),
         subdirectories: sub_dirs,
+        error: None,
+        skipped: None,
     })
 }
 
-fn print_usage() {
-    println!("tree [path]\n[param]=> parameter 'param' is optional;path is optional");
+//roots like `/`, `C:\` or `..` have no file name of their own
+fn root_name(path: &Path) -> String {
+    match path.file_name() {
+        Some(name) => name.to_string_lossy().into(),
+        None if path.as_os_str().is_empty() => ".".into(),
+        None => path.to_string_lossy().into(),
+    }
+}
+
+/// Renders a path for output with repeated separators collapsed into one.
+/// On Windows both `/` and `\` count as separators and are written as `\`.
+fn display_path(path: &Path) -> String {
+    normalize_separators(&path.to_string_lossy(), cfg!(windows))
 }
 
-fn print_tree(path: &PathBuf, tree: &Directory) {
-    const PIPE: &str = "\u{2502}\u{00A0}\u{00A0}"; // │
-    const TEE_PIPE: &str = "\u{251c}\u{2500}\u{2500} "; // ├──
-    const SPACES: &str = "\u{00A0}\u{00A0} "; // └─
-    const L_PIPE: &str = "\u{2514}\u{2500} "; // └─
+//`\` is an ordinary character in Unix names, so it's only a separator to convert on Windows
+fn posix_path(path: &str) -> String {
+    if cfg!(windows) {
+        path.replace('\\', "/")
+    } else {
+        path.to_string()
+    }
+}
+
+fn normalize_separators(path: &str, windows: bool) -> String {
+    let separator = if windows { '\\' } else { '/' };
+    let is_separator = |c: char| c == '/' || (windows && c == '\\');
+
+    let mut normalized = String::with_capacity(path.len());
+    let mut rest = path;
+    //a leading double separator on windows is a UNC or `\\?\` prefix, not a redundant one
+    if windows && path.len() >= 2 && path.chars().take(2).all(is_separator) {
+        normalized.push_str(r"\\");
+        rest = &path[2..];
+    }
+    for c in rest.chars() {
+        if !is_separator(c) {
+            normalized.push(c);
+        } else if !normalized.ends_with(separator) {
+            normalized.push(separator);
+        }
+    }
+    normalized
+}
 
-    println!("{}", path.to_string_lossy());
-    let (f, d) = visit(tree, "");
-    println!("{} files, {} directories", f, d);
+fn print_usage() {
+    println!("tree [path...] [options]\n[param]=> parameter 'param' is optional;path is optional");
+    println!(
+        "options:
+  --from-stdin             also read paths to show from stdin, one per line
+  -a                       show hidden files and directories
+  -F                       append / to directories, * to executables and @ to symlinks
+  --descend-hidden         walk into hidden directories
+  --diff <other_path>      compare against another directory
+  --no-config              don't load {CONFIG_FILE} from the root
+  --age-color              color files by modification age
+  --age-fresh <duration>   files newer than this are green (default 1d)
+  --age-recent <duration>  files newer than this are yellow (default 7d)
+  --detect-type            tag each file with its type, read from its first bytes
+  --loc                    count the lines of each text file and directory
+  --sparse                 flag files with far less disk allocated than their size (Unix)
+  --disk-usage             count file sizes as the space taken on disk, like du
+  --apparent-size          count file sizes as their length (default)
+  --short-names            show the 8.3 short name of entries that have one (Windows)
+  --timeout <duration>     stop walking after this long and print what was read
+  --max-entries <n>        stop walking after n entries
+  --on-limit <abort|truncate>
+                           fail past --max-entries, or print what was read (default abort)
+  --report-hidden          count the hidden entries left out in the summary line
+  --hash <algorithm>       show each file's crc32, md5 or sha256 digest
+  --as-mkdir               print mkdir/touch commands recreating the tree
+  --root-label <label>     print this instead of the root path at the top
+  --sort <order>           name (default), size, mtime, created, created-asc, count,
+                           count-asc or none
+  --quote-names            wrap names in double quotes, escaping as needed
+  --dirs-first             list directories before files
+  --group-order <groups>   list dirs, files and links in this order, e.g. dirs,files,links
+  --sort-dirs <order>      with --dirs-first or --group-order, order directories by this
+                           instead of --sort
+  --sort-files <order>     with --dirs-first or --group-order, order files and symlinks by
+                           this instead of --sort
+  --compare-names-unicode-normalized
+                           sort, filter and match duplicate names as NFC, so composed and
+                           decomposed accents are the same name
+  --no-ext                 show file names without their extension
+  --color <auto|always|never>  when to write colors (default auto)
+  --guide-color <color>    color of the tree connectors when coloring (default gray)
+  --highlight <pattern>    emphasize entries whose names match the glob
+  --ext <list>             show only files with one of these extensions, e.g. rs,toml,md
+  --no-extension           show only files without an extension, or add them to --ext
+  --prune                  leave out directories that end up without any files
+  --same-fs                don't descend into other filesystems
+  --bundles <show|opaque>  list macOS .app, .bundle and .framework dirs closed (default show)
+  --yaml                   print the tree as YAML
+  --tsv                    print one tab-separated row per entry
+  --markdown               print the tree as a nested Markdown list, for issues and docs
+  --flat                   print one path relative to the root per line
+  --flat-abs               like --flat, with every path starting at the root's name
+  --posix-paths            write / separators in --flat, --json, --yaml and --tsv paths
+  --trim-common-prefix     print the directories all --flat paths share once, as a header
+  --empty                  show only empty files and directories
+  --dirs-with-match <pattern>
+                           show only the directories holding a file that matches the glob
+  --duplicates             list file names found in more than one place, or contents with --hash
+  --deduplicate-symlink-targets
+                           list targets that more than one symlink points to
+  --regular-only           leave out FIFOs, sockets and devices, listing and counting only
+                           regular files, directories and symlinks
+  --portability-check      list names that other systems, mostly Windows, don't allow
+  --by-ext                 list the files grouped by extension instead of by directory
+  --top <n>                list only the n largest files with their sizes
+  --json                   print the tree as JSON
+  --json-indent <n>        spaces per level of --json, 0 for one line (default 2)
+  --jsonl                  stream one JSON object per entry while walking
+  --verify <manifest>      check the tree against a saved --json manifest
+  --confirm-large <n>      ask before printing more than n entries on a terminal
+  --pager                  page the output through $PAGER (default less -R)
+  --date                   show each file's modification time
+  --date-format <format>   strftime-style format for --date (default %FT%TZ)
+  --time-type <type>       mtime, atime, ctime or btime for --date and --age-color
+  --created                show when each file was created
+  --link-info <link|target>  show a symlink's own metadata or its target's (default link)
+  --resolve-links          show symlink targets as absolute paths
+  --max-symlink-resolutions <n>
+                           links followed per target before giving up (default 40)
+  --names-only             skip reading metadata, for speed on large trees
+  --report-only            print only the totals, not the tree
+  --total-only             print only the total size of the files, in bytes
+  --si                     with --total-only, print the total in powers of 1000 (kB, MB, ...)
+  --age-summary            add the oldest and newest file modification dates to the totals
+  --depth-histogram        also print how many entries there are at each depth
+  --per-dir-limit <n>      show at most n entries of each directory
+  --sample <n>             show only the first n files of each extension per directory
+  --skip-dir-over <size>   don't show the entries of directories larger than size, e.g. 500M
+  --size-precision <n>     decimals shown in sizes such as 4.2 GiB (default 1)
+  --size-if-over <size>    show the size of files bigger than this, e.g. 100M
+  --encoding <encoding>    utf8, utf8-bom or ascii connectors (default utf8)
+  --tree-chars <chars>     custom connectors as <pipe>,<tee>,<last>,<space>
+  --no-guides              indent entries without drawing the branch lines
+  --reverse-depth          draw the tree bottom-up, each directory below its entries
+  --collapse-after <n>     show directories deeper than n as one line with their totals
+  --width <n>              leave out annotations to fit lines in n columns, 0 for no limit
+                           (default the terminal's width)"
+    );
+}
+
+fn print_tree(
+    out: &mut dyn Write,
+    path: &Path,
+    tree: &Directory,
+    options: &DisplayOptions,
+) -> io::Result<()> {
+    if options.encoding == Encoding::Utf8Bom {
+        write!(out, "{}", Encoding::BOM)?;
+    }
+    let root = match &options.root_label {
+        Some(label) => label.clone(),
+        None => display_path(path),
+    };
+    let (f, d) = if options.reverse_depth {
+        let counts = visit_bottom_up(out, tree, path, "", options, 1, SystemTime::now())?;
+        writeln!(out, "{root}")?;
+        counts
+    } else {
+        writeln!(out, "{root}")?;
+        visit(out, tree, path, "", options, 1, SystemTime::now())?
+    };
+    let mut incomplete = options.age_range(tree);
+    if let Some(hidden) = options.hidden_skipped {
+        incomplete.push_str(&format!(" ({hidden} hidden)"));
+    }
+    if options.incomplete {
+        incomplete.push_str(" (incomplete)");
+    }
+    if let Some(limit) = options.truncated_at {
+        incomplete.push_str(&format!(" (truncated at {limit} entries)"));
+    }
+    match &options.loc {
+        Some(loc) => writeln!(
+            out,
+            "{} files, {} directories, {} lines{incomplete}",
+            f,
+            d,
+            loc.dir(tree)
+        )?,
+        None => writeln!(out, "{} files, {} directories{incomplete}", f, d)?,
+    }
+    if options.depth_histogram {
+        writeln!(out, "{}", Summary::of(tree).depth_histogram())?;
+    }
+    return Ok(());
 
-    fn visit(dir: &Directory, pre: &str) -> (usize, usize) {
+    fn visit(
+        out: &mut dyn Write,
+        dir: &Directory,
+        //where `dir` is, for what can't be told from its name
+        path: &Path,
+        pre: &str,
+        options: &DisplayOptions,
+        //of `dir`'s entries, 1 below the root
+        depth: usize,
+        now: SystemTime,
+    ) -> io::Result<(usize, usize)> {
+        let style = &options.style;
         let mut dir_count = 1;
         let mut file_count = 0;
 
-        let mut subdir_count = dir.subdirectories.len();
+        let (entries, sampled_out) = match options.sample {
+            Some(n) => sample_entries(&dir.subdirectories, n),
+            None => (dir.subdirectories.iter().collect(), Vec::new()),
+        };
+        //entries past --per-dir-limit are folded into one line but still counted
+        let shown = entries
+            .len()
+            .min(options.per_dir_limit.unwrap_or(usize::MAX));
+        let folded = &entries[shown..];
+        let mut subdir_count = shown + sampled_out.len() + usize::from(!folded.is_empty());
 
-        for entry in dir.subdirectories.iter() {
+        for entry in entries[..shown].iter().copied() {
             subdir_count -= 1;
             let prefix = pre;
-            let connector = if subdir_count == 0 { L_PIPE } else { TEE_PIPE };
+            let connector = if subdir_count == 0 {
+                &style.last
+            } else {
+                &style.tee
+            };
             match entry {
                 TreeEntry::FileNode(file) => {
                     file_count += 1;
-                    println!("{}{}{}", prefix, connector, file.name,);
+                    let head = format!("{}{}{}", prefix, connector, options.file_name(file, now));
+                    let annotations = options.file_annotations(file, display_width(&head));
+                    writeln!(out, "{head}{annotations}")?;
                 }
-                TreeEntry::SymlinkNode(_) => {
+                TreeEntry::SymlinkNode(link) => {
                     file_count += 1;
+                    let head = format!(
+                        "{}{}{} -> {}",
+                        prefix,
+                        connector,
+                        options.link_name(link),
+                        options.link_target(link)
+                    );
+                    let annotations = options.symlink_annotations(link, display_width(&head));
+                    writeln!(out, "{head}{annotations}")?;
                 }
                 TreeEntry::DirNode(dir_entry) => {
-                    println!("{}{}{}", prefix, connector, dir_entry.name);
+                    let dir_path = path.join(&dir_entry.name);
+                    let collapsed = options.is_collapsed(depth);
+                    let head = format!("{}{}{}", prefix, connector, options.dir_name(dir_entry));
+                    let annotations = options.dir_annotations(
+                        dir_entry,
+                        &dir_path,
+                        collapsed,
+                        display_width(&head),
+                    );
+                    writeln!(out, "{head}{annotations}")?;
+                    if collapsed {
+                        let totals = Summary::of(dir_entry);
+                        file_count += totals.files + totals.symlinks;
+                        dir_count += totals.directories;
+                        continue;
+                    }
                     let next_prefix = format!(
                         "{}{}",
                         prefix,
-                        if subdir_count == 0 { SPACES } else { PIPE }
+                        if subdir_count == 0 {
+                            &style.space
+                        } else {
+                            &style.pipe
+                        }
                     );
 
-                    let (f, d) = visit(dir_entry, &next_prefix);
+                    let (f, d) = visit(
+                        out,
+                        dir_entry,
+                        &dir_path,
+                        &next_prefix,
+                        options,
+                        depth + 1,
+                        now,
+                    )?;
                     file_count += f;
                     dir_count += d;
                 }
             }
         }
 
-        (file_count, dir_count)
+        for (extension, count) in sampled_out {
+            subdir_count -= 1;
+            let connector = if subdir_count == 0 {
+                &style.last
+            } else {
+                &style.tee
+            };
+            match extension {
+                Some(ext) => writeln!(
+                    out,
+                    "{pre}{connector}{} (+{count} more .{ext})",
+                    style.ellipsis
+                )?,
+                None => writeln!(
+                    out,
+                    "{pre}{connector}{} (+{count} more without extension)",
+                    style.ellipsis
+                )?,
+            }
+            file_count += count;
+        }
+
+        if !folded.is_empty() {
+            writeln!(
+                out,
+                "{}{}{} ({} more)",
+                pre,
+                style.last,
+                style.ellipsis,
+                folded.len()
+            )?;
+            for entry in folded {
+                match entry {
+                    TreeEntry::DirNode(dir_entry) => {
+                        let totals = Summary::of(dir_entry);
+                        file_count += totals.files + totals.symlinks;
+                        dir_count += totals.directories;
+                    }
+                    _ => file_count += 1,
+                }
+            }
+        }
+
+        Ok((file_count, dir_count))
     }
-}
 
-fn main() -> Result<()> {
-    let args = std::env::args();
+    //--reverse-depth: the tree flipped upside down but with siblings in their usual order, so
+    //a directory's entries come right above its own line and the root is printed last. The
+    //branch of a directory starts at its first entry's corner and runs down to the directory
+    fn visit_bottom_up(
+        out: &mut dyn Write,
+        dir: &Directory,
+        //where `dir` is, for what can't be told from its name
+        path: &Path,
+        pre: &str,
+        options: &DisplayOptions,
+        //of `dir`'s entries, 1 below the root
+        depth: usize,
+        now: SystemTime,
+    ) -> io::Result<(usize, usize)> {
+        let style = &options.style;
+        let mut dir_count = 1;
+        let mut file_count = 0;
 
-    let path = match args.skip(1).next() {
-        Some(p) => PathBuf::from_str(&p).context(format!("Path cannot be created from {p}"))?,
-        None => std::env::current_dir().context(format!("Cannot create starting path"))?,
-    };
-    let tree = walk_dir(&path)?;
-    print_tree(&path, &tree);
-    Ok(())
+        for (i, entry) in dir.subdirectories.iter().enumerate() {
+            let connector = if i == 0 { &style.first } else { &style.tee };
+            match entry {
+                TreeEntry::FileNode(file) => {
+                    file_count += 1;
+                    let head = format!("{}{}{}", pre, connector, options.file_name(file, now));
+                    let annotations = options.file_annotations(file, display_width(&head));
+                    writeln!(out, "{head}{annotations}")?;
+                }
+                TreeEntry::SymlinkNode(link) => {
+                    file_count += 1;
+                    let head = format!(
+                        "{}{}{} -> {}",
+                        pre,
+                        connector,
+                        options.link_name(link),
+                        options.link_target(link)
+                    );
+                    let annotations = options.symlink_annotations(link, display_width(&head));
+                    writeln!(out, "{head}{annotations}")?;
+                }
+                TreeEntry::DirNode(dir_entry) => {
+                    let dir_path = path.join(&dir_entry.name);
+                    let collapsed = options.is_collapsed(depth);
+                    if collapsed {
+                        let totals = Summary::of(dir_entry);
+                        file_count += totals.files + totals.symlinks;
+                        dir_count += totals.directories;
+                    } else {
+                        //nothing of this directory's branch is drawn above its first entry
+                        let next_prefix =
+                            format!("{}{}", pre, if i == 0 { &style.space } else { &style.pipe });
+                        let (f, d) = visit_bottom_up(
+                            out,
+                            dir_entry,
+                            &dir_path,
+                            &next_prefix,
+                            options,
+                            depth + 1,
+                            now,
+                        )?;
+                        file_count += f;
+                        dir_count += d;
+                    }
+                    let head = format!("{}{}{}", pre, connector, options.dir_name(dir_entry));
+                    let annotations = options.dir_annotations(
+                        dir_entry,
+                        &dir_path,
+                        collapsed,
+                        display_width(&head),
+                    );
+                    writeln!(out, "{head}{annotations}")?;
+                }
+            }
+        }
+
+        Ok((file_count, dir_count))
+    }
+}
+
+//asks on stderr and reads the answer from stdin
+fn confirm(question: &str) -> io::Result<bool> {
+    eprint!("{question}");
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(is_yes(&answer))
+}
+
+fn is_yes(answer: &str) -> bool {
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+//writes whichever output the arguments ask for
+fn render(
+    out: &mut dyn Write,
+    args: &cli::Args,
+    path: &Path,
+    tree: &Directory,
+    options: &WalkOptions,
+    display: &DisplayOptions,
+) -> Result<()> {
+    if let Some(other_path) = &args.diff {
+        //the other tree gets its own --max-entries budget, and --same-fs keeps it on its own
+        //filesystem rather than the first root's
+        let other_options = WalkOptions {
+            stay_on_filesystem: if args.same_fs {
+                platform::filesystem_id(other_path)
+            } else {
+                None
+            },
+            entries_kept: Cell::new(0),
+            limit_reached: Cell::new(false),
+            ..options.clone()
+        };
+        let mut other = read_tree(other_path, &other_options)?;
+        check_entry_limit(args, other_path, &other_options)?;
+        sort::sort_tree(&mut other, &args.sorting());
+        diff::print_diff(
+            out,
+            path,
+            other_path,
+            &diff::diff_trees(tree, &other),
+            &display.style,
+        )?;
+        return Ok(());
+    }
+
+    if let Some(manifest) = &args.verify {
+        #[cfg(feature = "json")]
+        return verify::verify(out, tree, manifest, display.hash);
+        #[cfg(not(feature = "json"))]
+        anyhow::bail!(
+            "--verify {} needs groot built with the json feature",
+            manifest.display()
+        );
+    }
+
+    if args.duplicates {
+        duplicates::print_duplicates(out, tree, args.hash, args.normalize_names)?;
+        return Ok(());
+    }
+
+    if args.shared_link_targets {
+        let max_resolutions = args
+            .max_symlink_resolutions
+            .unwrap_or(MAX_SYMLINK_RESOLUTIONS);
+        duplicates::print_shared_targets(out, tree, max_resolutions)?;
+        return Ok(());
+    }
+
+    if args.portability_check {
+        return portability::print_portability(out, tree);
+    }
+
+    if let Some(n) = args.top {
+        top::print_top(out, tree, n, args.quote_names)?;
+        return Ok(());
+    }
+
+    if args.by_ext {
+        by_ext::print_by_ext(out, tree, args.quote_names)?;
+        return Ok(());
+    }
+
+    if args.flat || args.flat_abs {
+        flat::print_flat(
+            out,
+            tree,
+            args.flat_abs,
+            args.quote_names,
+            args.trim_common_prefix,
+        )?;
+        return Ok(());
+    }
+
+    if args.tsv {
+        tsv::print_tsv(out, tree)?;
+        return Ok(());
+    }
+
+    if args.markdown {
+        let root = match &display.root_label {
+            Some(label) => label.clone(),
+            None => display_path(path),
+        };
+        markdown::print_markdown(out, &root, tree)?;
+        return Ok(());
+    }
+
+    if args.as_mkdir {
+        skeleton::print_mkdir_script(out, tree)?;
+        return Ok(());
+    }
+
+    if args.total_only {
+        let bytes = Summary::of(tree).bytes;
+        if args.si {
+            writeln!(out, "{}", summary::SiSize(bytes))?;
+        } else {
+            writeln!(out, "{bytes}")?;
+        }
+        return Ok(());
+    }
+
+    if args.report_only {
+        let summary = Summary::of(tree);
+        writeln!(out, "{summary}{}", display.age_range(tree))?;
+        if args.depth_histogram {
+            writeln!(out, "{}", summary.depth_histogram())?;
+        }
+        return Ok(());
+    }
+
+    if args.yaml {
+        #[cfg(feature = "yaml")]
+        return export::print_yaml(out, tree, display.hash);
+        #[cfg(not(feature = "yaml"))]
+        anyhow::bail!("--yaml needs groot built with the yaml feature");
+    }
+
+    if args.json {
+        #[cfg(feature = "json")]
+        return export::print_json(out, tree, args.json_indent.unwrap_or(2), display.hash);
+        #[cfg(not(feature = "json"))]
+        anyhow::bail!("--json needs groot built with the json feature");
+    }
+
+    print_tree(out, path, tree, display)?;
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    let cli_args: Vec<String> = std::env::args().skip(1).collect();
+    let mut args = match cli::Args::parse(cli_args.iter().cloned()) {
+        Ok(args) => args,
+        Err(err) => {
+            print_usage();
+            return Err(err);
+        }
+    };
+
+    let mut paths = args.paths.clone();
+    if args.from_stdin {
+        for line in io::stdin().lines() {
+            let line = line.context("unable to read paths from stdin")?;
+            if !line.trim().is_empty() {
+                paths.push(PathBuf::from(line));
+            }
+        }
+    }
+    //several paths are shown as one tree from the directory they have in common, while a
+    //single pattern like `src/*/tests` is shown as one tree per match
+    let roots = match paths.as_slice() {
+        [] => vec![std::env::current_dir().context("Cannot create starting path")?],
+        [pattern] if glob::is_pattern(pattern) && !pattern.exists() => {
+            let roots = glob::expand(pattern);
+            if roots.is_empty() {
+                anyhow::bail!("no matches for {}", display_path(pattern));
+            }
+            roots
+        }
+        [single] => vec![single.clone()],
+        several => vec![partial::root_for(several)],
+    };
+    //the config of the directory every root is in
+    let path = match roots.as_slice() {
+        [single] => single.clone(),
+        several => partial::common_ancestor(several),
+    };
+
+    let mut options = WalkOptions::default();
+    if !args.no_config {
+        if let Some(config) = Config::load(&path)? {
+            //config defaults go first so anything given on the command line wins
+            let mut config_args = Vec::new();
+            for (key, value) in config.defaults {
+                match config::default_to_args(&key, &value) {
+                    Ok(default_args) => config_args.extend(default_args),
+                    Err(err) => eprintln!("WARNING: ignoring {err:#}"),
+                }
+            }
+            args = cli::Args::parse(config_args.into_iter().chain(cli_args))?;
+            options.excludes = config.excludes;
+        }
+    }
+
+    //checked before the walk so a bad --tree-chars fails fast
+    let mut style = match &args.tree_chars {
+        Some(chars) => args.encoding.style().with_tree_chars(chars)?,
+        None => args.encoding.style(),
+    };
+    if args.no_guides {
+        style = style.without_guides();
+    }
+
+    options.extensions = args.extensions.clone();
+    options.extensionless = args.no_extension;
+    options.show_hidden = args.all;
+    options.recurse_hidden_dirs = args.descend_hidden;
+    options.names_only = args.names_only;
+    options.disk_usage = args.disk_usage;
+    options.opaque_bundles = args.opaque_bundles;
+    if args.names_only && args.skip_dir_over.is_some() {
+        anyhow::bail!("--skip-dir-over needs file sizes, which --names-only doesn't read");
+    }
+    if args.names_only && args.size_if_over.is_some() {
+        anyhow::bail!("--size-if-over needs file sizes, which --names-only doesn't read");
+    }
+    if args.names_only && args.total_only {
+        anyhow::bail!("--total-only needs file sizes, which --names-only doesn't read");
+    }
+    if args.si && !args.total_only {
+        anyhow::bail!("--si only applies to --total-only");
+    }
+    if args.names_only && args.top.is_some() {
+        anyhow::bail!("--top needs file sizes, which --names-only doesn't read");
+    }
+    if args.names_only && args.empty {
+        anyhow::bail!("--empty needs file sizes, which --names-only doesn't read");
+    }
+    if args.names_only && args.disk_usage {
+        anyhow::bail!("--disk-usage needs file sizes, which --names-only doesn't read");
+    }
+    if args.reverse_depth && (args.per_dir_limit.is_some() || args.sample.is_some()) {
+        anyhow::bail!("--reverse-depth shows every entry, so it can't fold them with --per-dir-limit or --sample");
+    }
+    if args.dirs_first && args.group_order.is_some() {
+        anyhow::bail!("--group-order already places the directories, so it can't be combined with --dirs-first");
+    }
+    if args.sparse && cfg!(not(unix)) {
+        anyhow::bail!("--sparse is only supported on Unix");
+    }
+    if args.short_names && cfg!(not(windows)) {
+        anyhow::bail!("--short-names is only supported on Windows");
+    }
+    //a timeout too far off to be an instant never runs out
+    options.deadline = args
+        .timeout
+        .and_then(|timeout| Instant::now().checked_add(timeout));
+    options.max_entries = args.max_entries;
+    options.normalize_names = args.normalize_names;
+    #[cfg(not(feature = "unicode-normalization"))]
+    if args.normalize_names {
+        anyhow::bail!(
+            "--compare-names-unicode-normalized needs groot built with the unicode-normalization feature"
+        );
+    }
+
+    //every root goes through the same output, so they share one pager
+    let mut out = Output::new(args.pager);
+    let mut show_roots = || -> Result<()> {
+        for (i, root) in roots.iter().enumerate() {
+            if i > 0 {
+                writeln!(out)?;
+            }
+            options.timed_out.set(false);
+            options.hidden_skipped.set(0);
+            options.entries_kept.set(0);
+            options.limit_reached.set(false);
+            if args.same_fs {
+                options.stay_on_filesystem = platform::filesystem_id(root);
+            }
+            show(&mut out, &args, root, &paths, &options, style.clone())?;
+        }
+        Ok(())
+    };
+    let result = show_roots();
+    let finished = out.finish();
+    ignore_broken_pipe(result.and_then(|()| finished.map_err(Into::into)))
+}
+
+//--on-limit: fails a walk that hit --max-entries, unless it is to be truncated with a warning
+fn check_entry_limit(args: &cli::Args, path: &Path, options: &WalkOptions) -> Result<()> {
+    if !options.limit_reached.get() {
+        return Ok(());
+    }
+    let limit = options.max_entries.unwrap_or_default();
+    if !args.truncate_at_limit {
+        anyhow::bail!(
+            "{} has more than {limit} entries, see --on-limit truncate to show the first ones",
+            display_path(path)
+        );
+    }
+    eprintln!("WARNING: the walk stopped at --max-entries {limit}, so the output is truncated");
+    Ok(())
+}
+
+/// Walks one root and writes it out the way `args` asks for.
+fn show(
+    out: &mut dyn Write,
+    args: &cli::Args,
+    path: &PathBuf,
+    paths: &[PathBuf],
+    options: &WalkOptions,
+    style: RenderStyle,
+) -> Result<()> {
+    if args.jsonl {
+        if paths.len() > 1 || !path.is_dir() {
+            anyhow::bail!("--jsonl streams the walk of a single directory");
+        }
+        if args.empty
+            || args.skip_dir_over.is_some()
+            || args.prune
+            || args.dirs_with_match.is_some()
+        {
+            anyhow::bail!("--jsonl writes entries as they are read, so it can't filter them by --empty, --skip-dir-over, --prune or --dirs-with-match");
+        }
+        #[cfg(feature = "json")]
+        {
+            jsonl::stream_jsonl(out, path, options, &args.sorting(), args.regular_only)?;
+            if options.timed_out.get() {
+                eprintln!(
+                    "WARNING: the walk took longer than --timeout, so the output is incomplete"
+                );
+            }
+            return check_entry_limit(args, path, options);
+        }
+        #[cfg(not(feature = "json"))]
+        anyhow::bail!("--jsonl needs groot built with the json feature");
+    }
+
+    let mut tree = if paths.len() > 1 {
+        partial::partial_tree(path, paths, options)?
+    } else {
+        read_tree(path, options)?
+    };
+    check_entry_limit(args, path, options)?;
+    sort::sort_tree(&mut tree, &args.sorting());
+    if args.posix_paths && (args.flat || args.flat_abs || args.json || args.yaml || args.tsv) {
+        tree.use_posix_separators();
+    }
+    if let Some(limit) = args.skip_dir_over {
+        summary::skip_dirs_over(&mut tree, limit);
+    }
+    if args.regular_only {
+        tree.retain_regular();
+    }
+    if args.prune {
+        tree.prune();
+    }
+    if let Some(pattern) = &args.dirs_with_match {
+        tree.retain_dirs_with_match(pattern, args.normalize_names);
+    }
+    if args.empty {
+        tree.retain_empty();
+    }
+    if options.timed_out.get() {
+        eprintln!("WARNING: the walk took longer than --timeout, so the tree is incomplete");
+    }
+
+    if let Some(limit) = args.confirm_large {
+        let totals = Summary::of(&tree);
+        //the root itself isn't an entry
+        let entries = totals.files + totals.directories - 1 + totals.symlinks;
+        if entries > limit
+            && io::IsTerminal::is_terminal(&io::stdin())
+            && !confirm(&format!(
+                "{} has {entries} entries, more than {limit}. Print them? [y/N] ",
+                display_path(path)
+            ))?
+        {
+            return Ok(());
+        }
+    }
+
+    let mut display = DisplayOptions::default();
+    if args.age_color {
+        let mut age_colors = AgeColors::default();
+        age_colors.fresh = args.age_fresh.unwrap_or(age_colors.fresh);
+        age_colors.recent = args.age_recent.unwrap_or(age_colors.recent);
+        display.age_colors = Some(age_colors);
+    }
+    display.hash = args.hash;
+    display.highlights = args.highlights.clone();
+    display.normalize_names = args.normalize_names;
+    display.link_info = args.link_info;
+    display.encoding = args.encoding;
+    display.style = style;
+    display.per_dir_limit = args.per_dir_limit;
+    display.sample = args.sample;
+    display.reverse_depth = args.reverse_depth;
+    display.collapse_after = args.collapse_after;
+    display.width = match args.width {
+        Some(0) => None,
+        Some(width) => Some(width),
+        None if io::IsTerminal::is_terminal(&io::stdout()) => platform::terminal_width(),
+        None => None,
+    };
+    display.strip_extensions = args.no_ext;
+    display.root_label = args.root_label.clone();
+    display.time_type = args.time_type;
+    display.created = args.created;
+    display.quote_names = args.quote_names;
+    display.classify = args.classify;
+    display.detect_type = args.detect_type;
+    display.depth_histogram = args.depth_histogram;
+    display.opaque_bundles = args.opaque_bundles;
+    display.size_precision = args.size_precision;
+    display.size_if_over = args.size_if_over;
+    display.resolve_links = args.resolve_links;
+    display.max_symlink_resolutions = args.max_symlink_resolutions;
+    display.sparse = args.sparse;
+    display.short_names = args.short_names;
+    display.incomplete = options.timed_out.get();
+    display.truncated_at = options
+        .limit_reached
+        .get()
+        .then_some(options.max_entries)
+        .flatten();
+    display.age_summary = args.age_summary;
+    if args.report_hidden {
+        display.hidden_skipped = Some(options.hidden_skipped.get());
+    }
+    if args.loc {
+        display.loc = Some(loc::LineCounter::default());
+    }
+    display.colorize = color::should_colorize(
+        args.color,
+        io::IsTerminal::is_terminal(&io::stdout()),
+        &color::Env::from_process(),
+    );
+    if display.colorize {
+        display.style = std::mem::take(&mut display.style)
+            .with_guide_color(args.guide_color.unwrap_or(Color::Gray));
+    }
+    if args.date || args.date_format.is_some() {
+        display.date_format = Some(args.date_format.clone().unwrap_or_default());
+    }
+
+    render(out, args, path, &tree, options, &display)
+}
+
+//the reader went away early, e.g. by quitting the pager or closing a `| head`
+fn ignore_broken_pipe(result: Result<()>) -> Result<()> {
+    match result {
+        Err(err)
+            if err
+                .downcast_ref::<io::Error>()
+                .is_some_and(|err| err.kind() == io::ErrorKind::BrokenPipe) =>
+        {
+            Ok(())
+        }
+        result => result,
+    }
+}
+
+#[test]
+fn normalize_unix_separators() {
+    assert_eq!(
+        normalize_separators("/home//user///src/", false),
+        "/home/user/src/"
+    );
+    assert_eq!(
+        normalize_separators("dir\\name//file", false),
+        "dir\\name/file"
+    );
+}
+
+#[test]
+fn normalize_windows_separators() {
+    assert_eq!(
+        normalize_separators(r"C:\Users\\me/src//lib", true),
+        r"C:\Users\me\src\lib"
+    );
+    assert_eq!(
+        normalize_separators(r"\\server\\share//dir", true),
+        r"\\server\share\dir"
+    );
+    assert_eq!(normalize_separators(r"\\?\C:\deep", true), r"\\?\C:\deep");
+}
+
+#[cfg(windows)]
+#[test]
+fn posix_paths_use_forward_slashes() {
+    let mut tree = Directory {
+        name: r"C:\".to_string(),
+        subdirectories: vec![TreeEntry::SymlinkNode(Symlink {
+            name: "current".to_string(),
+            path: PathBuf::from(r"C:\current"),
+            target: r"..\lib\v2".to_string(),
+            link_metadata: None,
+            target_metadata: None,
+        })],
+        error: None,
+        skipped: None,
+    };
+    tree.use_posix_separators();
+    assert_eq!(tree.name, "C:/");
+    match &tree.subdirectories[0] {
+        TreeEntry::SymlinkNode(link) => assert_eq!(link.target, "../lib/v2"),
+        other => panic!("expected a symlink, got {other:?}"),
+    }
+}
+
+//creates an empty scratch directory unique to the calling test
+#[cfg(test)]
+fn test_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("groot-bin-{}-{}", std::process::id(), name));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+//a regular file that exists only in the tree, with no metadata
+#[cfg(test)]
+fn test_file(name: &str, size: Option<u64>) -> TreeEntry {
+    TreeEntry::FileNode(File {
+        name: name.to_string(),
+        path: name.into(),
+        size,
+        metadata: None,
+        kind: FileKind::Regular,
+    })
+}
+
+//a directory that was read without errors
+#[cfg(test)]
+fn test_dir_node(name: &str, entries: Vec<TreeEntry>) -> TreeEntry {
+    TreeEntry::DirNode(Directory {
+        name: name.to_string(),
+        subdirectories: entries,
+        error: None,
+        skipped: None,
+    })
+}
+
+#[test]
+fn root_name_without_file_name() {
+    assert_eq!(root_name(Path::new("/")), "/");
+    assert_eq!(root_name(Path::new(".")), ".");
+    //a drive root on Windows, an ordinary name elsewhere; either way it's kept whole
+    assert_eq!(root_name(Path::new(r"C:\")), r"C:\");
+    assert_eq!(root_name(Path::new("..")), "..");
+    assert_eq!(root_name(Path::new("")), ".");
+    assert_eq!(root_name(Path::new("/home/user/.")), "user");
+
+    let dir = test_dir("root_name");
+    fs::write(dir.join("a.txt"), "").unwrap();
+    let tree = walk_dir(&dir.join("."), &WalkOptions::default()).unwrap();
+    assert_eq!(tree.name, dir.file_name().unwrap().to_string_lossy());
+    assert_eq!(tree.subdirectories.len(), 1);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn symlink_keeps_link_and_target_metadata() {
+    let dir = test_dir("link_info");
+    fs::write(dir.join("target.txt"), "0123456789").unwrap();
+    std::os::unix::fs::symlink("target.txt", dir.join("link")).unwrap();
+
+    let tree = walk_dir(&dir, &WalkOptions::default()).unwrap();
+    let link = tree
+        .subdirectories
+        .iter()
+        .find_map(|entry| match entry {
+            TreeEntry::SymlinkNode(link) => Some(link),
+            _ => None,
+        })
+        .expect("symlink is walked as a symlink");
+    assert_eq!(link.target, "target.txt");
+    assert!(link
+        .metadata(LinkInfo::Link)
+        .unwrap()
+        .file_type()
+        .is_symlink());
+    assert_eq!(link.metadata(LinkInfo::Target).unwrap().len(), 10);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn only_empty_entries_are_kept() {
+    let dir = test_dir("empty");
+    fs::create_dir_all(dir.join("a").join("empty_dir")).unwrap();
+    fs::create_dir_all(dir.join("b").join("full")).unwrap();
+    fs::write(dir.join("a").join("blank.txt"), "").unwrap();
+    fs::write(dir.join("a").join("notes.txt"), "notes").unwrap();
+    fs::write(dir.join("b").join("full").join("data"), "data").unwrap();
+    fs::write(dir.join("zero"), "").unwrap();
+
+    let mut tree = walk_dir(&dir, &WalkOptions::default()).unwrap();
+    sort::sort_tree(&mut tree, &Default::default());
+    tree.retain_empty();
+    let mut out = Vec::new();
+    flat::print_flat(&mut out, &tree, false, false, false).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "a/\na/blank.txt\na/empty_dir/\nzero\n"
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn dirs_with_match_keep_no_files() {
+    let dir = test_dir("dirs_with_match");
+    fs::create_dir_all(dir.join("core").join("tests")).unwrap();
+    fs::create_dir_all(dir.join("cli").join("src")).unwrap();
+    fs::create_dir_all(dir.join("docs")).unwrap();
+    fs::write(dir.join("core").join("tests").join("walk_test.rs"), "").unwrap();
+    fs::write(dir.join("core").join("lib.rs"), "").unwrap();
+    fs::write(dir.join("cli").join("cli_test.rs"), "").unwrap();
+    fs::write(dir.join("cli").join("src").join("main.rs"), "").unwrap();
+    fs::write(dir.join("docs").join("test.md"), "").unwrap();
+
+    let mut tree = walk_dir(&dir, &WalkOptions::default()).unwrap();
+    sort::sort_tree(&mut tree, &Default::default());
+    tree.retain_dirs_with_match("*_test.rs", false);
+    let mut out = Vec::new();
+    flat::print_flat(&mut out, &tree, false, false, false).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "cli/\ncore/\ncore/tests/\n"
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn prune_keeps_only_dirs_with_extensionless_files() {
+    let dir = test_dir("no_extension");
+    fs::create_dir_all(dir.join("bin")).unwrap();
+    fs::create_dir_all(dir.join("src").join("empty")).unwrap();
+    fs::write(dir.join("bin").join("deploy"), "").unwrap();
+    fs::write(dir.join("src").join("main.rs"), "").unwrap();
+    fs::write(dir.join("README"), "").unwrap();
+    fs::write(dir.join(".bashrc"), "").unwrap();
+
+    let options = WalkOptions {
+        extensionless: true,
+        show_hidden: true,
+        ..Default::default()
+    };
+    let flat = |tree: &Directory| {
+        let mut out = Vec::new();
+        flat::print_flat(&mut out, tree, false, false, false).unwrap();
+        String::from_utf8(out).unwrap()
+    };
+    let mut tree = walk_dir(&dir, &options).unwrap();
+    sort::sort_tree(&mut tree, &Default::default());
+    assert_eq!(
+        flat(&tree),
+        ".bashrc\nbin/\nbin/deploy\nREADME\nsrc/\nsrc/empty/\n"
+    );
+    tree.prune();
+    assert_eq!(flat(&tree), ".bashrc\nbin/\nbin/deploy\nREADME\n");
+
+    let options = WalkOptions {
+        extensions: vec!["rs".to_string()],
+        ..options
+    };
+    let mut tree = walk_dir(&dir.join("src"), &options).unwrap();
+    sort::sort_tree(&mut tree, &Default::default());
+    assert_eq!(flat(&tree), "empty/\nmain.rs\n");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn resolved_targets_are_absolute() {
+    let dir = test_dir("resolve_links");
+    fs::create_dir_all(dir.join("shared").join("docs")).unwrap();
+    fs::create_dir(dir.join("app")).unwrap();
+    std::os::unix::fs::symlink("../shared/docs", dir.join("app").join("docs")).unwrap();
+    std::os::unix::fs::symlink("../missing", dir.join("app").join("broken")).unwrap();
+
+    let tree = walk_dir(&dir.join("app"), &WalkOptions::default()).unwrap();
+    let mut targets: Vec<(&str, PathBuf)> = tree
+        .subdirectories
+        .iter()
+        .map(|entry| match entry {
+            TreeEntry::SymlinkNode(link) => (
+                link.name.as_str(),
+                link.resolved_target(MAX_SYMLINK_RESOLUTIONS).unwrap(),
+            ),
+            other => panic!("expected a symlink, got {other:?}"),
+        })
+        .collect();
+    targets.sort();
+    let canonical = dir.canonicalize().unwrap();
+    assert_eq!(
+        targets,
+        vec![
+            ("broken", dir.join("app").join("../missing")),
+            ("docs", canonical.join("shared").join("docs")),
+        ]
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn opaque_bundles_keep_only_their_size() {
+    let dir = test_dir("bundles");
+    let contents = dir.join("Tool.app").join("Contents");
+    fs::create_dir_all(contents.join("MacOS")).unwrap();
+    fs::write(contents.join("Info.plist"), "12345").unwrap();
+    fs::write(contents.join("MacOS").join("tool"), "123").unwrap();
+    fs::create_dir(dir.join("src")).unwrap();
+    fs::write(dir.join("src").join("main.rs"), "").unwrap();
+
+    let options = WalkOptions {
+        opaque_bundles: true,
+        ..Default::default()
+    };
+    let mut tree = walk_dir(&dir, &options).unwrap();
+    sort::sort_tree(&mut tree, &Default::default());
+    let dirs: Vec<(&str, usize, Option<u64>)> = tree
+        .subdirectories
+        .iter()
+        .map(|entry| match entry {
+            TreeEntry::DirNode(dir) => (dir.name.as_str(), dir.subdirectories.len(), dir.skipped),
+            other => panic!("expected a directory, got {other:?}"),
+        })
+        .collect();
+    assert_eq!(dirs, vec![("src", 1, None), ("Tool.app", 0, Some(8))]);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn per_dir_limit_folds_but_counts() {
+    let tree = Directory {
+        name: "root".to_string(),
+        subdirectories: vec![
+            test_file("a", None),
+            test_dir_node(
+                "sub",
+                vec![
+                    test_file("x", None),
+                    test_file("y", None),
+                    test_file("z", None),
+                ],
+            ),
+            test_file("b", None),
+            test_dir_node("late", vec![test_file("w", None)]),
+        ],
+        error: None,
+        skipped: None,
+    };
+    let display = DisplayOptions {
+        per_dir_limit: Some(2),
+        style: RenderStyle::ASCII,
+        ..Default::default()
+    };
+
+    let mut out = Vec::new();
+    print_tree(&mut out, Path::new("root"), &tree, &display).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "root
+|-- a
+|-- sub
+|  |-- x
+|  |-- y
+|  `- ... (1 more)
+`- ... (2 more)
+6 files, 3 directories
+"
+    );
+}
+
+#[test]
+fn no_ext_strips_only_the_last_extension() {
+    let display = DisplayOptions {
+        strip_extensions: true,
+        ..Default::default()
+    };
+    let name = |name: &str| {
+        let file = File {
+            name: name.to_string(),
+            path: name.into(),
+            size: None,
+            metadata: None,
+            kind: FileKind::Regular,
+        };
+        display.file_name(&file, SystemTime::now())
+    };
+    assert_eq!(name("song.flac"), "song");
+    assert_eq!(name("backup.tar.gz"), "backup.tar");
+    assert_eq!(name(".env"), ".env");
+    assert_eq!(name("README"), "README");
+}
+
+#[test]
+fn only_yes_confirms() {
+    assert!(is_yes("y\n"));
+    assert!(is_yes(" YES \r\n"));
+    assert!(!is_yes("\n"));
+    assert!(!is_yes("no\n"));
+    assert!(!is_yes("yep"));
+}
+
+#[test]
+fn quote_awkward_names() {
+    assert_eq!(quoted("plain.txt"), r#""plain.txt""#);
+    assert_eq!(quoted("my notes.txt"), r#""my notes.txt""#);
+    assert_eq!(quoted(r#"say "hi""#), r#""say \"hi\"""#);
+    assert_eq!(quoted(r"C:\temp"), r#""C:\\temp""#);
+    assert_eq!(quoted("two\nlines"), r#""two\nlines""#);
+    assert_eq!(quoted("bell\u{7}"), r#""bell\x07""#);
+}
+
+#[cfg(windows)]
+#[test]
+fn walk_goes_past_max_path() {
+    let dir = test_dir("long_paths");
+    let mut deep = dir.clone();
+    while deep.as_os_str().len() <= 300 {
+        deep.push("a_fairly_long_directory_name");
+    }
+    fs::create_dir_all(platform::long_path(&deep)).unwrap();
+    fs::write(platform::long_path(&deep.join("leaf.txt")), "").unwrap();
+
+    let tree = walk_dir(&dir, &WalkOptions::default()).unwrap();
+    let mut level = &tree;
+    while let Some(TreeEntry::DirNode(child)) = level.subdirectories.first() {
+        level = child;
+    }
+    assert_eq!(level.subdirectories[0].name(), "leaf.txt");
+
+    fs::remove_dir_all(platform::long_path(&dir)).unwrap();
+}
+
+#[test]
+fn timeout_lists_unread_dirs() {
+    let dir = test_dir("timeout");
+    fs::create_dir_all(dir.join("a").join("b")).unwrap();
+    fs::write(dir.join("top.txt"), "").unwrap();
+
+    //already past the deadline, so only the root is read
+    let options = WalkOptions {
+        deadline: Some(Instant::now()),
+        ..Default::default()
+    };
+    let mut tree = walk_dir(&dir, &options).unwrap();
+    sort::sort_tree(&mut tree, &Default::default());
+    assert!(options.timed_out.get());
+    match &tree.subdirectories[0] {
+        TreeEntry::DirNode(a) => {
+            assert!(a.subdirectories.is_empty());
+            assert_eq!(a.error.as_deref(), Some("walk timed out"));
+        }
+        other => panic!("expected a directory, got {other:?}"),
+    }
+    assert_eq!(tree.subdirectories[1].name(), "top.txt");
+
+    let display = DisplayOptions {
+        incomplete: true,
+        ..Default::default()
+    };
+    let mut out = Vec::new();
+    print_tree(&mut out, &dir, &tree, &display).unwrap();
+    assert!(String::from_utf8(out)
+        .unwrap()
+        .ends_with("1 files, 2 directories (incomplete)\n"));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn max_entries_stops_the_walk() {
+    let dir = test_dir("max_entries");
+    fs::create_dir_all(dir.join("a").join("b")).unwrap();
+    fs::write(dir.join("a").join("one.txt"), "").unwrap();
+    fs::write(dir.join("top.txt"), "").unwrap();
+
+    let options = WalkOptions {
+        max_entries: Some(2),
+        ..Default::default()
+    };
+    let tree = walk_dir(&dir, &options).unwrap();
+    assert!(options.limit_reached.get());
+    let totals = Summary::of(&tree);
+    //the root isn't an entry
+    assert_eq!(totals.files + totals.directories - 1, 2);
+
+    let options = WalkOptions {
+        max_entries: Some(4),
+        ..Default::default()
+    };
+    walk_dir(&dir, &options).unwrap();
+    assert!(!options.limit_reached.get());
+
+    let display = DisplayOptions {
+        truncated_at: Some(2),
+        ..Default::default()
+    };
+    let mut out = Vec::new();
+    print_tree(&mut out, &dir, &tree, &display).unwrap();
+    assert!(String::from_utf8(out)
+        .unwrap()
+        .ends_with(" (truncated at 2 entries)\n"));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn hidden_entries_are_counted() {
+    let dir = test_dir("report_hidden");
+    fs::create_dir_all(dir.join(".git").join("objects")).unwrap();
+    fs::create_dir(dir.join("src")).unwrap();
+    fs::write(dir.join(".env"), "").unwrap();
+    fs::write(dir.join("src").join(".keep"), "").unwrap();
+    fs::write(dir.join("src").join("main.rs"), "").unwrap();
+
+    let options = WalkOptions::default();
+    let tree = walk_dir(&dir, &options).unwrap();
+    //.git's contents aren't read, so only .git itself counts
+    assert_eq!(options.hidden_skipped.get(), 3);
+
+    let display = DisplayOptions {
+        hidden_skipped: Some(options.hidden_skipped.get()),
+        ..Default::default()
+    };
+    let mut out = Vec::new();
+    print_tree(&mut out, &dir, &tree, &display).unwrap();
+    assert!(String::from_utf8(out)
+        .unwrap()
+        .ends_with("1 files, 2 directories (3 hidden)\n"));
+
+    let options = WalkOptions {
+        show_hidden: true,
+        ..Default::default()
+    };
+    walk_dir(&dir, &options).unwrap();
+    assert_eq!(options.hidden_skipped.get(), 0);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn sample_keeps_first_files_per_extension() {
+    let tree = Directory {
+        name: "photos".to_string(),
+        subdirectories: vec![
+            test_file("a.jpg", None),
+            test_file("b.jpg", None),
+            test_file("c.jpg", None),
+            test_file("notes", None),
+            test_dir_node("raw", vec![test_file("d.cr2", None)]),
+            test_file("e.png", None),
+            test_file("f.jpg", None),
+            test_file("README", None),
+        ],
+        error: None,
+        skipped: None,
+    };
+    let display = DisplayOptions {
+        sample: Some(1),
+        style: RenderStyle::ASCII,
+        ..Default::default()
+    };
+
+    let mut out = Vec::new();
+    print_tree(&mut out, Path::new("photos"), &tree, &display).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "photos
+|-- a.jpg
+|-- notes
+|-- raw
+|  `- d.cr2
+|-- e.png
+|-- ... (+3 more .jpg)
+`- ... (+1 more without extension)
+8 files, 2 directories
+"
+    );
+}
+
+#[test]
+fn reverse_depth_prints_entries_above_their_dir() {
+    let tree = Directory {
+        name: "root".to_string(),
+        subdirectories: vec![
+            test_dir_node("first", vec![test_file("a", None)]),
+            test_file("b", None),
+            test_dir_node(
+                "src",
+                vec![
+                    test_file("lib.rs", None),
+                    test_dir_node("bin", vec![test_file("x", None)]),
+                ],
+            ),
+        ],
+        error: None,
+        skipped: None,
+    };
+    let display = DisplayOptions {
+        reverse_depth: true,
+        style: RenderStyle::ASCII,
+        ..Default::default()
+    };
+
+    let mut out = Vec::new();
+    print_tree(&mut out, Path::new("root"), &tree, &display).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "   ,- a
+,- first
+|-- b
+|  ,- lib.rs
+|  |  ,- x
+|  |-- bin
+|-- src
+root
+4 files, 4 directories
+"
+    );
+}
+
+#[test]
+fn collapse_after_summarizes_deeper_dirs() {
+    let tree = Directory {
+        name: "root".to_string(),
+        subdirectories: vec![
+            test_dir_node(
+                "web",
+                vec![
+                    test_dir_node(
+                        "node_modules",
+                        vec![
+                            test_file("a.js", Some(1024)),
+                            test_dir_node("lib", vec![test_file("b.js", Some(1024))]),
+                        ],
+                    ),
+                    test_file("index.js", Some(10)),
+                ],
+            ),
+            test_file("README", Some(5)),
+        ],
+        error: None,
+        skipped: None,
+    };
+    let display = DisplayOptions {
+        collapse_after: Some(1),
+        style: RenderStyle::ASCII,
+        ..Default::default()
+    };
+
+    let mut out = Vec::new();
+    print_tree(&mut out, Path::new("root"), &tree, &display).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "root
+|-- web
+|  |-- node_modules [2 files, 2.0 KiB]
+|  `- index.js
+`- README
+4 files, 4 directories
+"
+    );
+    assert_eq!(
+        [
+            thousands(0),
+            thousands(999),
+            thousands(1204),
+            thousands(1234567)
+        ],
+        ["0", "999", "1,204", "1,234,567"]
+    );
+}
+
+#[test]
+fn width_leaves_out_the_least_important_columns() {
+    let display = DisplayOptions {
+        width: Some(30),
+        ..Default::default()
+    };
+    let annotations = || {
+        vec![
+            (Column::Size, "1.2 KiB".to_string()),
+            (Column::Status, "error: denied".to_string()),
+            (Column::Date, "2024-06-12".to_string()),
+            (Column::Hash, "d41d8cd98f00".to_string()),
+        ]
+    };
+    assert_eq!(
+        display.fitted(annotations(), 0),
+        " [1.2 KiB, error: denied]"
+    );
+    //the status stays even when it doesn't fit
+    assert_eq!(display.fitted(annotations(), 29), " [error: denied]");
+    assert_eq!(
+        DisplayOptions::default().fitted(annotations(), 100),
+        " [1.2 KiB, error: denied, 2024-06-12, d41d8cd98f00]"
+    );
+    assert_eq!(
+        display_width("\x1b[1;34msrc\x1b[0m \u{251c}\u{2500}\u{2500} a"),
+        9
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn special_files_are_listed_but_not_read() {
+    let dir = test_dir("special_files");
+    let _listener = std::os::unix::net::UnixListener::bind(dir.join("app.sock")).unwrap();
+    let status = std::process::Command::new("mkfifo")
+        .arg(dir.join("pipe"))
+        .status()
+        .unwrap();
+    assert!(status.success());
+    fs::write(dir.join("plain.txt"), "text\n").unwrap();
+
+    let mut tree = walk_dir(&dir, &WalkOptions::default()).unwrap();
+    sort::sort_tree(&mut tree, &Default::default());
+    let kinds: Vec<(&str, FileKind)> = tree
+        .subdirectories
+        .iter()
+        .map(|entry| match entry {
+            TreeEntry::FileNode(file) => (file.name.as_str(), file.kind),
+            other => panic!("expected a file, got {other:?}"),
+        })
+        .collect();
+    assert_eq!(
+        kinds,
+        vec![
+            ("app.sock", FileKind::Socket),
+            ("pipe", FileKind::Fifo),
+            ("plain.txt", FileKind::Regular)
+        ]
+    );
+
+    //opening the FIFO would block the test, so --loc and --detect-type mustn't
+    let display = DisplayOptions {
+        classify: true,
+        detect_type: true,
+        loc: Some(loc::LineCounter::default()),
+        style: RenderStyle::ASCII,
+        ..Default::default()
+    };
+    let mut out = Vec::new();
+    print_tree(&mut out, Path::new("special"), &tree, &display).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "special
+|-- app.sock= [socket]
+|-- pipe| [fifo]
+`- plain.txt [text, 1 lines]
+3 files, 1 directories, 1 lines
+"
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn sizes_only_above_the_threshold() {
+    let tree = Directory {
+        name: "root".to_string(),
+        subdirectories: vec![
+            test_file("big.iso", Some(3 << 30)),
+            test_file("limit", Some(1 << 20)),
+            test_file("small.txt", Some(12)),
+        ],
+        error: None,
+        skipped: None,
+    };
+    let display = DisplayOptions {
+        size_if_over: Some(1 << 20),
+        style: RenderStyle::ASCII,
+        ..Default::default()
+    };
+
+    let mut out = Vec::new();
+    print_tree(&mut out, Path::new("root"), &tree, &display).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "root
+|-- big.iso [3.0 GiB]
+|-- limit
+`- small.txt
+3 files, 1 directories
+"
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn links_to_an_ancestor_are_cycles() {
+    use std::os::unix::fs::symlink;
+
+    let dir = test_dir("link_cycle");
+    fs::create_dir_all(dir.join("a").join("b")).unwrap();
+    symlink("..", dir.join("a").join("b").join("up")).unwrap();
+    symlink(".", dir.join("a").join("here")).unwrap();
+    symlink("a/b", dir.join("down")).unwrap();
+    symlink("missing", dir.join("broken")).unwrap();
+
+    let link = |path: PathBuf| Symlink {
+        name: root_name(&path),
+        target: fs::read_link(&path).unwrap().to_string_lossy().into(),
+        path,
+        link_metadata: None,
+        target_metadata: None,
+    };
+    let up = link(dir.join("a").join("b").join("up"));
+    assert!(up.points_to_ancestor());
+    assert_eq!(
+        DisplayOptions::default().symlink_annotations(&up, 0),
+        " [cycle]"
+    );
+    assert!(link(dir.join("a").join("here")).points_to_ancestor());
+    assert!(!link(dir.join("down")).points_to_ancestor());
+    assert!(!link(dir.join("broken")).points_to_ancestor());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn link_chains_stop_after_the_limit() {
+    use std::os::unix::fs::symlink;
+
+    let dir = test_dir("link_chain");
+    fs::write(dir.join("end"), "").unwrap();
+    symlink("end", dir.join("one")).unwrap();
+    symlink("one", dir.join("two")).unwrap();
+    symlink("loop-b", dir.join("loop-a")).unwrap();
+    symlink("loop-a", dir.join("loop-b")).unwrap();
+
+    assert_eq!(follow_links(&dir.join("two"), 2), Some(dir.join("end")));
+    assert_eq!(follow_links(&dir.join("two"), 1), None);
+    assert_eq!(follow_links(&dir.join("end"), 0), Some(dir.join("end")));
+    assert_eq!(
+        follow_links(&dir.join("loop-a"), MAX_SYMLINK_RESOLUTIONS),
+        None
+    );
+
+    let mut tree = walk_dir(&dir, &WalkOptions::default()).unwrap();
+    sort::sort_tree(&mut tree, &Default::default());
+    let display = DisplayOptions {
+        resolve_links: true,
+        max_symlink_resolutions: Some(1),
+        ..Default::default()
+    };
+    let lines: Vec<String> = tree
+        .subdirectories
+        .iter()
+        .filter_map(|entry| match entry {
+            TreeEntry::SymlinkNode(link) => Some(format!(
+                "{} -> {}{}",
+                link.name,
+                display.link_target(link),
+                display.symlink_annotations(link, 0)
+            )),
+            _ => None,
+        })
+        .collect();
+    let end = dir.canonicalize().unwrap().join("end");
+    assert_eq!(
+        lines,
+        vec![
+            "loop-a -> loop-b [too many levels of symlinks]".to_string(),
+            "loop-b -> loop-a [too many levels of symlinks]".to_string(),
+            format!("one -> {}", end.display()),
+            "two -> one [too many levels of symlinks]".to_string(),
+        ]
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn regular_only_drops_special_files() {
+    let dir = test_dir("regular_only");
+    fs::create_dir(dir.join("run")).unwrap();
+    let _listener =
+        std::os::unix::net::UnixListener::bind(dir.join("run").join("app.sock")).unwrap();
+    fs::write(dir.join("run").join("app.pid"), "42").unwrap();
+
+    let mut tree = walk_dir(&dir, &WalkOptions::default()).unwrap();
+    assert_eq!(Summary::of(&tree).files, 2);
+    tree.retain_regular();
+    assert_eq!(Summary::of(&tree).files, 1);
+    match &tree.subdirectories[0] {
+        TreeEntry::DirNode(run) => assert_eq!(run.subdirectories[0].name(), "app.pid"),
+        other => panic!("expected run to be a directory, got {other:?}"),
+    }
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(feature = "unicode-normalization")]
+#[test]
+fn normalized_names_match_filters() {
+    let options = WalkOptions {
+        excludes: vec!["cafe\u{301}/".to_string()],
+        normalize_names: true,
+        ..Default::default()
+    };
+    assert!(options.is_excluded("caf\u{e9}", true));
+    assert!(!options.is_excluded("caf\u{e9}", false));
+    let raw = WalkOptions {
+        normalize_names: false,
+        ..options
+    };
+    assert!(!raw.is_excluded("caf\u{e9}", true));
+
+    let display = DisplayOptions {
+        highlights: vec!["*\u{e9}*".to_string()],
+        normalize_names: true,
+        ..Default::default()
+    };
+    assert!(display.is_highlighted("re\u{301}sume\u{301}.pdf"));
+}
+
+#[test]
+fn diff_walk_has_its_own_entry_limit() {
+    let dir = test_dir("diff_entry_limit");
+    for side in ["old", "new"] {
+        fs::create_dir_all(dir.join(side)).unwrap();
+        fs::write(dir.join(side).join("a.txt"), "").unwrap();
+        fs::write(dir.join(side).join("b.txt"), "").unwrap();
+    }
+    let (old, new) = (dir.join("old"), dir.join("new"));
+    let args = cli::Args::parse(
+        ["--diff", new.to_str().unwrap(), "--max-entries", "2"]
+            .into_iter()
+            .map(String::from),
+    )
+    .unwrap();
+    let options = WalkOptions {
+        max_entries: args.max_entries,
+        ..Default::default()
+    };
+    let tree = walk_dir(&old, &options).unwrap();
+
+    let mut out = Vec::new();
+    render(
+        &mut out,
+        &args,
+        &old,
+        &tree,
+        &options,
+        &DisplayOptions::default(),
+    )
+    .unwrap();
+    assert!(String::from_utf8(out)
+        .unwrap()
+        .ends_with("0 added, 0 removed, 0 changed\n"));
+
+    //and a bigger other tree fails the way the first one would
+    fs::write(new.join("c.txt"), "").unwrap();
+    let tree = walk_dir(&old, &options).unwrap();
+    assert!(render(
+        &mut Vec::new(),
+        &args,
+        &old,
+        &tree,
+        &options,
+        &DisplayOptions::default()
+    )
+    .is_err());
+
+    fs::remove_dir_all(&dir).unwrap();
 }
@@ -0,0 +1,84 @@
+use std::io::{self, Write};
+
+use crate::{Directory, TreeEntry};
+
+/// Prints the tree as a nested Markdown bullet list, two spaces per level, with the root as the
+/// only top-level item. Directories are bold and files and symlinks are inline code, so names
+/// read the same wherever the list is pasted.
+pub fn print_markdown(out: &mut dyn Write, root: &str, tree: &Directory) -> io::Result<()> {
+    writeln!(out, "- **{}**", escape(root))?;
+    return visit(out, tree, 1);
+
+    fn visit(out: &mut dyn Write, dir: &Directory, level: usize) -> io::Result<()> {
+        let indent = "  ".repeat(level);
+        for entry in dir.subdirectories.iter() {
+            match entry {
+                TreeEntry::DirNode(dir_entry) => {
+                    writeln!(out, "{indent}- **{}**", escape(&dir_entry.name))?;
+                    visit(out, dir_entry, level + 1)?;
+                }
+                TreeEntry::FileNode(file) => writeln!(out, "{indent}- {}", code(&file.name))?,
+                TreeEntry::SymlinkNode(link) => writeln!(
+                    out,
+                    "{indent}- {} -> {}",
+                    code(&link.name),
+                    code(&link.target)
+                )?,
+            }
+        }
+        Ok(())
+    }
+}
+
+//backslashes in front of everything that could start emphasis, a link, html or a heading
+fn escape(name: &str) -> String {
+    let mut escaped = String::with_capacity(name.len());
+    for c in name.chars() {
+        if "\\`*_{}[]<>()#+-.!|~".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+//escapes don't work inside a code span, so it's fenced with one backtick more than the name's
+//longest run of them, and padded when the name starts or ends with one
+fn code(name: &str) -> String {
+    let longest_run = name.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat(longest_run + 1);
+    if name.starts_with('`') || name.ends_with('`') {
+        format!("{fence} {name} {fence}")
+    } else {
+        format!("{fence}{name}{fence}")
+    }
+}
+
+#[test]
+fn nested_list_with_escaped_names() {
+    use crate::{test_dir_node, test_file};
+
+    let tree = Directory {
+        name: "root".to_string(),
+        subdirectories: vec![
+            test_dir_node(
+                "__init__",
+                vec![test_file("main.rs", None), test_file("a`b", None)],
+            ),
+            test_file("`quoted`", None),
+        ],
+        error: None,
+        skipped: None,
+    };
+    let mut out = Vec::new();
+    print_markdown(&mut out, "my_repo", &tree).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "- **my\\_repo**
+  - **\\_\\_init\\_\\_**
+    - `main.rs`
+    - ``a`b``
+  - `` `quoted` ``
+"
+    );
+}
@@ -0,0 +1,46 @@
+use std::borrow::Cow;
+
+/// `--compare-names-unicode-normalized`: `name` in Unicode NFC when `normalize` is set, so a
+/// decomposed `e\u{301}` compares equal to a composed `é`, as macOS filesystems may store
+/// either. Only used for comparing; names are still printed as they were read.
+#[cfg(feature = "unicode-normalization")]
+pub fn comparable(name: &str, normalize: bool) -> Cow<'_, str> {
+    use unicode_normalization::{is_nfc_quick, IsNormalized, UnicodeNormalization};
+
+    if !normalize || is_nfc_quick(name.chars()) == IsNormalized::Yes {
+        Cow::Borrowed(name)
+    } else {
+        Cow::Owned(name.nfc().collect())
+    }
+}
+
+//main refuses the option without the feature, so names are always compared as read
+#[cfg(not(feature = "unicode-normalization"))]
+pub fn comparable(name: &str, _normalize: bool) -> Cow<'_, str> {
+    Cow::Borrowed(name)
+}
+
+/// `glob::glob_match` with both the pattern and the name made `comparable`.
+pub fn glob_match(pattern: &str, name: &str, normalize: bool) -> bool {
+    crate::glob::glob_match(
+        &comparable(pattern, normalize),
+        &comparable(name, normalize),
+    )
+}
+
+#[cfg(feature = "unicode-normalization")]
+#[test]
+fn decomposed_names_compose() {
+    assert_eq!(comparable("cafe\u{301}", true), "caf\u{e9}");
+    assert_eq!(comparable("caf\u{e9}", true), "caf\u{e9}");
+    assert_eq!(comparable("cafe\u{301}", false), "cafe\u{301}");
+    assert!(matches!(comparable("plain.txt", true), Cow::Borrowed(_)));
+}
+
+#[cfg(feature = "unicode-normalization")]
+#[test]
+fn patterns_match_either_form() {
+    assert!(glob_match("cafe\u{301}*", "caf\u{e9}.txt", true));
+    assert!(glob_match("caf\u{e9}*", "cafe\u{301}.txt", true));
+    assert!(!glob_match("caf\u{e9}*", "cafe\u{301}.txt", false));
+}
@@ -0,0 +1,107 @@
+use std::{
+    env,
+    io::{self, IsTerminal, Write},
+    process::{Child, ChildStdin, Command, Stdio},
+};
+
+const DEFAULT_PAGER: &str = "less -R";
+
+/// A running pager process that the rendered output is written into.
+pub struct Pager {
+    child: Child,
+    stdin: io::BufWriter<ChildStdin>,
+}
+
+impl Pager {
+    /// Starts `$PAGER` (or `less -R`) when stdout is a terminal.
+    /// Returns `None` when output is redirected or the pager can't be started,
+    /// in which case output should go straight to stdout.
+    pub fn spawn() -> Option<Self> {
+        if !io::stdout().is_terminal() {
+            return None;
+        }
+
+        let command = env::var("PAGER")
+            .ok()
+            .filter(|pager| !pager.trim().is_empty())
+            .unwrap_or_else(|| DEFAULT_PAGER.to_string());
+        let mut parts = command.split_whitespace();
+        let mut child = Command::new(parts.next()?)
+            .args(parts)
+            .stdin(Stdio::piped())
+            .spawn()
+            .ok()?;
+        let stdin = child
+            .stdin
+            .take()
+            .expect("pager is spawned with a piped stdin");
+
+        Some(Self {
+            child,
+            stdin: io::BufWriter::new(stdin),
+        })
+    }
+
+    /// The pager's input, buffered so a render isn't written to the pipe line by line.
+    pub fn stdin(&mut self) -> &mut io::BufWriter<ChildStdin> {
+        &mut self.stdin
+    }
+
+    /// Flushes and closes the pager's input and waits for the user to quit it.
+    pub fn wait(mut self) -> io::Result<()> {
+        //the pager is waited for even if it was quit before taking everything
+        let flushed = self.stdin.flush();
+        drop(self.stdin);
+        self.child.wait()?;
+        flushed
+    }
+}
+
+/// Where the output of every root goes: the pager with `--pager`, buffered stdout otherwise.
+/// The pager is only started by the first write, so a `--confirm-large` question is asked
+/// before it takes over the terminal.
+pub struct Output {
+    page: bool,
+    pager: Option<Pager>,
+    stdout: io::BufWriter<io::Stdout>,
+}
+
+impl Output {
+    pub fn new(page: bool) -> Self {
+        Self {
+            page,
+            pager: None,
+            stdout: io::BufWriter::new(io::stdout()),
+        }
+    }
+
+    /// Flushes stdout, or waits for the pager to be quit.
+    pub fn finish(mut self) -> io::Result<()> {
+        self.stdout.flush()?;
+        match self.pager {
+            Some(pager) => pager.wait(),
+            None => Ok(()),
+        }
+    }
+
+    fn target(&mut self) -> &mut dyn Write {
+        if self.page {
+            self.page = false;
+            self.pager = Pager::spawn();
+        }
+        match &mut self.pager {
+            Some(pager) => pager.stdin(),
+            None => &mut self.stdout,
+        }
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.target().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.target().flush()
+    }
+}
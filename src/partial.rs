@@ -0,0 +1,164 @@
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+
+use crate::{platform, root_name, walk_dir, Directory, File, Symlink, TreeEntry, WalkOptions};
+
+/// The deepest directory containing every path, going by their components alone.
+/// Paths that share nothing (or nothing is given) have `.` as their ancestor.
+pub fn common_ancestor(paths: &[PathBuf]) -> PathBuf {
+    let mut paths = paths.iter();
+    let mut ancestor: Vec<Component> = match paths.next() {
+        Some(first) => first.components().collect(),
+        None => return PathBuf::from("."),
+    };
+    for path in paths {
+        let shared = ancestor
+            .iter()
+            .zip(path.components())
+            .take_while(|(a, b)| **a == *b)
+            .count();
+        ancestor.truncate(shared);
+    }
+
+    let ancestor: PathBuf = ancestor.into_iter().collect();
+    if ancestor.as_os_str().is_empty() {
+        PathBuf::from(".")
+    } else {
+        ancestor
+    }
+}
+
+/// The directory a tree of `paths` is rooted at: their common ancestor, or its parent
+/// when that ancestor is one of the given files.
+pub fn root_for(paths: &[PathBuf]) -> PathBuf {
+    let ancestor = common_ancestor(paths);
+    if ancestor.is_dir() && !ancestor.is_symlink() {
+        return ancestor;
+    }
+    ancestor
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or(Path::new("."))
+        .to_path_buf()
+}
+
+/// Builds one tree rooted at `root_path` that holds only the branches leading to `paths`.
+/// A given directory is walked in full; a given file or symlink appears on its own.
+pub fn partial_tree(
+    root_path: &Path,
+    paths: &[PathBuf],
+    options: &WalkOptions,
+) -> Result<Directory> {
+    let mut root = Directory {
+        name: root_name(root_path),
+        subdirectories: Vec::new(),
+        error: None,
+        skipped: None,
+    };
+    for path in paths {
+        let relative = path.strip_prefix(root_path).unwrap_or(path);
+        let mut names: Vec<String> = relative
+            .components()
+            .filter_map(|component| match component {
+                Component::Normal(name) => Some(name.to_string_lossy().into_owned()),
+                _ => None,
+            })
+            .collect();
+        let Some(leaf) = names.pop() else {
+            //the ancestor itself was given, so everything below it is shown
+            root.subdirectories = walk_dir(&root_path.to_path_buf(), options)?.subdirectories;
+            continue;
+        };
+
+        let mut dir = &mut root;
+        for name in &names {
+            dir = dir.child_dir(name);
+        }
+        if dir.subdirectories.iter().any(|entry| entry.name() == leaf) {
+            continue;
+        }
+        dir.subdirectories.push(entry_for(path, leaf, options)?);
+    }
+    Ok(root)
+}
+
+fn entry_for(path: &Path, name: String, options: &WalkOptions) -> Result<TreeEntry> {
+    let link_metadata =
+        fs::symlink_metadata(path).context(format!("unable to read path: {path:#?}"))?;
+    Ok(if link_metadata.is_symlink() {
+        TreeEntry::SymlinkNode(Symlink {
+            name,
+            path: path.to_path_buf(),
+            target: fs::read_link(path)?.to_string_lossy().into(),
+            link_metadata: Some(link_metadata),
+            target_metadata: fs::metadata(path).ok(),
+        })
+    } else if link_metadata.is_dir() {
+        let mut dir = walk_dir(&path.to_path_buf(), options)?;
+        dir.name = name;
+        TreeEntry::DirNode(dir)
+    } else {
+        TreeEntry::FileNode(File {
+            name,
+            path: path.to_path_buf(),
+            size: Some(options.file_size(&link_metadata, path)),
+            kind: platform::file_kind(&link_metadata.file_type()),
+            metadata: Some(link_metadata),
+        })
+    })
+}
+
+#[test]
+fn ancestor_of_scattered_paths() {
+    let paths = |paths: &[&str]| paths.iter().map(PathBuf::from).collect::<Vec<_>>();
+    assert_eq!(
+        common_ancestor(&paths(&[
+            "/repo/src/a.rs",
+            "/repo/src/bin/b.rs",
+            "/repo/src/c.rs"
+        ])),
+        PathBuf::from("/repo/src")
+    );
+    assert_eq!(
+        common_ancestor(&paths(&["/repo/src", "/repo/tests/t.rs"])),
+        PathBuf::from("/repo")
+    );
+    assert_eq!(
+        common_ancestor(&paths(&["/a/b", "/a/bc"])),
+        PathBuf::from("/a")
+    );
+    assert_eq!(
+        common_ancestor(&paths(&["src", "tests"])),
+        PathBuf::from(".")
+    );
+    assert_eq!(common_ancestor(&[]), PathBuf::from("."));
+}
+
+#[test]
+fn partial_tree_keeps_only_given_branches() {
+    let dir = crate::test_dir("partial");
+    fs::create_dir_all(dir.join("src").join("bin")).unwrap();
+    fs::create_dir(dir.join("docs")).unwrap();
+    fs::write(dir.join("src").join("lib.rs"), "").unwrap();
+    fs::write(dir.join("src").join("other.rs"), "").unwrap();
+    fs::write(dir.join("src").join("bin").join("tool.rs"), "").unwrap();
+    fs::write(dir.join("docs").join("guide.md"), "").unwrap();
+
+    let paths = [dir.join("src").join("lib.rs"), dir.join("src").join("bin")];
+    let root_path = root_for(&paths);
+    assert_eq!(root_path, dir.join("src"));
+    assert_eq!(root_for(&paths[..1]), dir.join("src"));
+    let tree = partial_tree(&root_path, &paths, &WalkOptions::default()).unwrap();
+    let names: Vec<&str> = tree.subdirectories.iter().map(TreeEntry::name).collect();
+    assert_eq!(names, vec!["lib.rs", "bin"]);
+    match &tree.subdirectories[1] {
+        TreeEntry::DirNode(bin) => assert_eq!(bin.subdirectories[0].name(), "tool.rs"),
+        other => panic!("expected bin to be a directory, got {other:?}"),
+    }
+
+    fs::remove_dir_all(&dir).unwrap();
+}
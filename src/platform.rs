@@ -0,0 +1,483 @@
+//helpers whose implementation differs per operating system
+
+use std::{
+    fs::{FileType, Metadata},
+    path::{Path, PathBuf},
+};
+
+use crate::FileKind;
+
+/// Identifies the filesystem a path lives on, for `--same-fs`.
+#[cfg(unix)]
+pub type FilesystemId = u64;
+#[cfg(windows)]
+pub type FilesystemId = String;
+
+/// The device id on Unix. On Windows this is the drive or UNC share of the canonical path,
+/// so volumes mounted into a folder of another drive aren't told apart.
+/// `None` when the path can't be inspected.
+#[cfg(unix)]
+pub fn filesystem_id(path: &Path) -> Option<FilesystemId> {
+    use std::os::unix::fs::MetadataExt;
+
+    path.metadata().ok().map(|metadata| metadata.dev())
+}
+
+#[cfg(windows)]
+pub fn filesystem_id(path: &Path) -> Option<FilesystemId> {
+    use std::path::Component;
+
+    match path.canonicalize().ok()?.components().next()? {
+        Component::Prefix(prefix) => Some(prefix.as_os_str().to_string_lossy().to_uppercase()),
+        _ => None,
+    }
+}
+
+/// Whether `-F` marks a file with `*` and color shows it as a program: any of the execute bits
+/// on Unix, and on Windows, which has no such bit, a `.exe`, `.bat`, `.cmd` or `.com` extension.
+#[cfg(unix)]
+pub fn is_executable(meta: &Metadata, _path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    meta.is_file() && meta.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(windows)]
+pub fn is_executable(meta: &Metadata, path: &Path) -> bool {
+    const EXTENSIONS: [&str; 4] = ["exe", "bat", "cmd", "com"];
+
+    meta.is_file()
+        && path.extension().is_some_and(|ext| {
+            EXTENSIONS
+                .iter()
+                .any(|executable| ext.eq_ignore_ascii_case(executable))
+        })
+}
+
+/// `--sparse`: the bytes actually allocated for a file, from its 512-byte block count.
+#[cfg(unix)]
+pub fn allocated_bytes(meta: &Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+
+    meta.blocks() * 512
+}
+
+/// `--disk-usage`: the space a file takes up on disk, like `du` counts it. On Unix that is the
+/// allocated blocks, and on Windows the size the volume reports after compression and sparse
+/// ranges. Elsewhere, or when Windows can't tell, it is the file's length.
+#[cfg(unix)]
+pub fn disk_usage(meta: &Metadata, _path: &Path) -> u64 {
+    allocated_bytes(meta)
+}
+
+#[cfg(windows)]
+pub fn disk_usage(meta: &Metadata, path: &Path) -> u64 {
+    use std::os::windows::ffi::OsStrExt;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetCompressedFileSizeW(file_name: *const u16, file_size_high: *mut u32) -> u32;
+        fn GetLastError() -> u32;
+    }
+
+    let wide: Vec<u16> = long_path(path)
+        .as_os_str()
+        .encode_wide()
+        .chain(Some(0))
+        .collect();
+    let mut high = 0;
+    let low = unsafe { GetCompressedFileSizeW(wide.as_ptr(), &mut high) };
+    //all ones is also a valid low half, so only the last error tells a failure apart
+    if low == u32::MAX && unsafe { GetLastError() } != 0 {
+        return meta.len();
+    }
+    u64::from(high) << 32 | u64::from(low)
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn disk_usage(meta: &Metadata, _path: &Path) -> u64 {
+    meta.len()
+}
+
+/// FIFOs, sockets and devices from the file type, which a directory listing reads without
+/// opening the file. Anything else that isn't a directory or symlink is a regular file.
+#[cfg(unix)]
+pub fn file_kind(file_type: &FileType) -> FileKind {
+    use std::os::unix::fs::FileTypeExt;
+
+    if file_type.is_fifo() {
+        FileKind::Fifo
+    } else if file_type.is_socket() {
+        FileKind::Socket
+    } else if file_type.is_block_device() {
+        FileKind::BlockDevice
+    } else if file_type.is_char_device() {
+        FileKind::CharDevice
+    } else {
+        FileKind::Regular
+    }
+}
+
+#[cfg(not(unix))]
+pub fn file_kind(_file_type: &FileType) -> FileKind {
+    FileKind::Regular
+}
+
+/// The path to hand to filesystem calls so deep trees past Windows' 260 character `MAX_PATH`
+/// can still be read: absolute, with the `\\?\` extended-length prefix. Unchanged elsewhere.
+#[cfg(not(windows))]
+pub fn long_path(path: &Path) -> PathBuf {
+    path.to_path_buf()
+}
+
+#[cfg(windows)]
+pub fn long_path(path: &Path) -> PathBuf {
+    use std::path::{Component, Prefix};
+
+    //extended-length paths skip normalization, so `.`, `..` and `/` have to be resolved first
+    let Ok(absolute) = std::path::absolute(path) else {
+        return path.to_path_buf();
+    };
+    let Some(text) = absolute.to_str() else {
+        return absolute;
+    };
+    match absolute.components().next() {
+        Some(Component::Prefix(prefix)) => match prefix.kind() {
+            Prefix::Disk(_) => PathBuf::from(format!(r"\\?\{text}")),
+            Prefix::UNC(..) => PathBuf::from(format!(r"\\?\UNC\{}", &text[2..])),
+            //already verbatim, or a device path
+            _ => absolute,
+        },
+        _ => absolute,
+    }
+}
+
+/// `--short-names`: the 8.3 name Windows keeps for `path`, e.g. `PROGRA~1` for `Program Files`.
+/// `None` when there is none: the volume doesn't generate them, the long name already fits, or
+/// the path can't be read. Always `None` elsewhere.
+#[cfg(not(windows))]
+pub fn short_name(_path: &Path) -> Option<String> {
+    None
+}
+
+#[cfg(windows)]
+pub fn short_name(path: &Path) -> Option<String> {
+    use std::{
+        ffi::OsString,
+        os::windows::ffi::{OsStrExt, OsStringExt},
+    };
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetShortPathNameW(long_path: *const u16, short_path: *mut u16, length: u32) -> u32;
+    }
+
+    let long: Vec<u16> = long_path(path)
+        .as_os_str()
+        .encode_wide()
+        .chain(Some(0))
+        .collect();
+    //asked with an empty buffer first, the length needed comes back, nul included
+    let needed = unsafe { GetShortPathNameW(long.as_ptr(), std::ptr::null_mut(), 0) };
+    if needed == 0 {
+        return None;
+    }
+    let mut buffer = vec![0u16; needed as usize];
+    //now the length written, without the nul, and only smaller than the buffer on success
+    let written = unsafe { GetShortPathNameW(long.as_ptr(), buffer.as_mut_ptr(), needed) };
+    if written == 0 || written >= needed {
+        return None;
+    }
+    buffer.truncate(written as usize);
+    let short = PathBuf::from(OsString::from_wide(&buffer));
+    let short = short.file_name()?.to_string_lossy().into_owned();
+    let long = path.file_name()?.to_string_lossy();
+    (!short.eq_ignore_ascii_case(&long)).then_some(short)
+}
+
+/// How many columns wide the terminal standard output goes to is, for `--width`: `$COLUMNS`
+/// when it is set, or else what the terminal reports. `None` when neither knows.
+pub fn terminal_width() -> Option<usize> {
+    std::env::var("COLUMNS")
+        .ok()
+        .and_then(|columns| columns.trim().parse().ok())
+        .filter(|&columns| columns > 0)
+        .or_else(reported_width)
+}
+
+#[cfg(unix)]
+fn reported_width() -> Option<usize> {
+    use std::ffi::{c_int, c_ulong};
+
+    #[repr(C)]
+    struct WinSize {
+        rows: u16,
+        columns: u16,
+        x_pixels: u16,
+        y_pixels: u16,
+    }
+
+    extern "C" {
+        fn ioctl(fd: c_int, request: c_ulong, ...) -> c_int;
+    }
+
+    #[cfg(all(
+        any(target_os = "linux", target_os = "android"),
+        not(any(
+            target_arch = "mips",
+            target_arch = "mips64",
+            target_arch = "powerpc",
+            target_arch = "powerpc64",
+            target_arch = "sparc64"
+        ))
+    ))]
+    const TIOCGWINSZ: c_ulong = 0x5413;
+    //macOS and the BSDs, and Linux on the architectures that took its numbering from them
+    #[cfg(not(all(
+        any(target_os = "linux", target_os = "android"),
+        not(any(
+            target_arch = "mips",
+            target_arch = "mips64",
+            target_arch = "powerpc",
+            target_arch = "powerpc64",
+            target_arch = "sparc64"
+        ))
+    )))]
+    const TIOCGWINSZ: c_ulong = 0x40087468;
+
+    let mut size = WinSize {
+        rows: 0,
+        columns: 0,
+        x_pixels: 0,
+        y_pixels: 0,
+    };
+    let result = unsafe { ioctl(1, TIOCGWINSZ, &mut size as *mut WinSize) };
+    (result == 0 && size.columns > 0).then_some(usize::from(size.columns))
+}
+
+#[cfg(windows)]
+fn reported_width() -> Option<usize> {
+    use std::ffi::c_void;
+
+    #[repr(C)]
+    struct Coord {
+        x: i16,
+        y: i16,
+    }
+
+    #[repr(C)]
+    struct SmallRect {
+        left: i16,
+        top: i16,
+        right: i16,
+        bottom: i16,
+    }
+
+    #[repr(C)]
+    struct ConsoleScreenBufferInfo {
+        size: Coord,
+        cursor_position: Coord,
+        attributes: u16,
+        window: SmallRect,
+        maximum_window_size: Coord,
+    }
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetStdHandle(std_handle: u32) -> *mut c_void;
+        fn GetConsoleScreenBufferInfo(
+            console: *mut c_void,
+            info: *mut ConsoleScreenBufferInfo,
+        ) -> i32;
+    }
+
+    //STD_OUTPUT_HANDLE
+    const STANDARD_OUTPUT: u32 = -11i32 as u32;
+
+    let mut info = ConsoleScreenBufferInfo {
+        size: Coord { x: 0, y: 0 },
+        cursor_position: Coord { x: 0, y: 0 },
+        attributes: 0,
+        window: SmallRect {
+            left: 0,
+            top: 0,
+            right: 0,
+            bottom: 0,
+        },
+        maximum_window_size: Coord { x: 0, y: 0 },
+    };
+    let succeeded =
+        unsafe { GetConsoleScreenBufferInfo(GetStdHandle(STANDARD_OUTPUT), &mut info) } != 0;
+    let columns = info.window.right - info.window.left + 1;
+    (succeeded && columns > 0).then_some(columns as usize)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn reported_width() -> Option<usize> {
+    None
+}
+
+/// Undoes [`long_path`] for output, dropping the `\\?\` prefix that `canonicalize` also adds on
+/// Windows. Unchanged elsewhere.
+#[cfg(not(windows))]
+pub fn short_path(path: &Path) -> PathBuf {
+    path.to_path_buf()
+}
+
+#[cfg(windows)]
+pub fn short_path(path: &Path) -> PathBuf {
+    let Some(text) = path.to_str() else {
+        return path.to_path_buf();
+    };
+    if let Some(share) = text.strip_prefix(r"\\?\UNC\") {
+        PathBuf::from(format!(r"\\{share}"))
+    } else if let Some(disk) = text
+        .strip_prefix(r"\\?\")
+        .filter(|rest| rest.get(1..2) == Some(":"))
+    {
+        PathBuf::from(disk)
+    } else {
+        path.to_path_buf()
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn proc_is_another_filesystem() {
+    let tmp = filesystem_id(&std::env::temp_dir());
+    assert!(tmp.is_some());
+    assert_eq!(tmp, filesystem_id(&std::env::temp_dir().join(".")));
+    if Path::new("/proc/self").exists() {
+        assert_ne!(tmp, filesystem_id(Path::new("/proc")));
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn special_files_have_their_own_kind() {
+    let dir = std::env::temp_dir().join(format!("groot-{}-file-kinds", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let socket = dir.join("socket");
+    let _listener = std::os::unix::net::UnixListener::bind(&socket).unwrap();
+    let kind = |path: &Path| file_kind(&std::fs::symlink_metadata(path).unwrap().file_type());
+
+    assert_eq!(kind(&socket), FileKind::Socket);
+    assert_eq!(kind(Path::new("/dev/null")), FileKind::CharDevice);
+    std::fs::write(dir.join("plain"), "").unwrap();
+    assert_eq!(kind(&dir.join("plain")), FileKind::Regular);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn disk_usage_counts_allocated_blocks() {
+    let path = std::env::temp_dir().join(format!("groot-{}-usage", std::process::id()));
+    //all hole, on filesystems that keep them
+    let file = std::fs::File::create(&path).unwrap();
+    file.set_len(1 << 20).unwrap();
+    drop(file);
+    let meta = path.metadata().unwrap();
+    assert_eq!(disk_usage(&meta, &path), allocated_bytes(&meta));
+    assert!(disk_usage(&meta, &path) <= meta.len());
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn execute_bits_make_a_file_executable() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let path = std::env::temp_dir().join(format!("groot-{}-script.sh", std::process::id()));
+    std::fs::write(&path, "#!/bin/sh\n").unwrap();
+    for (mode, executable) in [(0o644, false), (0o744, true), (0o654, true)] {
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(mode)).unwrap();
+        assert_eq!(
+            is_executable(&path.metadata().unwrap(), &path),
+            executable,
+            "{mode:o}"
+        );
+    }
+    //directories are searchable, not executable
+    let dir = std::env::temp_dir();
+    assert!(!is_executable(&dir.metadata().unwrap(), &dir));
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn holes_are_not_allocated() {
+    let path = std::env::temp_dir().join(format!("groot-{}-sparse", std::process::id()));
+    let file = std::fs::File::create(&path).unwrap();
+    file.set_len(64 * 1024 * 1024).unwrap();
+    let meta = file.metadata().unwrap();
+    assert_eq!(meta.len(), 64 * 1024 * 1024);
+    assert!(allocated_bytes(&meta) < meta.len());
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[cfg(windows)]
+#[test]
+fn executable_extensions() {
+    let dir = std::env::temp_dir().join(format!("groot-{}-executables", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    for (name, executable) in [
+        ("setup.EXE", true),
+        ("build.bat", true),
+        ("run.cmd", true),
+        ("notes.txt", false),
+        ("exe", false),
+    ] {
+        let path = dir.join(name);
+        std::fs::write(&path, "").unwrap();
+        assert_eq!(
+            is_executable(&path.metadata().unwrap(), &path),
+            executable,
+            "{name}"
+        );
+    }
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(windows)]
+#[test]
+fn long_paths_get_the_extended_prefix() {
+    assert_eq!(
+        long_path(Path::new(r"C:\data\.\logs\..\src")),
+        Path::new(r"\\?\C:\data\src")
+    );
+    assert_eq!(
+        long_path(Path::new(r"\\server\share\dir")),
+        Path::new(r"\\?\UNC\server\share\dir")
+    );
+    assert_eq!(
+        long_path(Path::new(r"\\?\C:\already")),
+        Path::new(r"\\?\C:\already")
+    );
+    for path in [r"C:\data\src", r"\\server\share\dir"] {
+        assert_eq!(short_path(&long_path(Path::new(path))), Path::new(path));
+    }
+}
+
+#[cfg(windows)]
+#[test]
+fn short_names_differ_from_long_ones() {
+    let dir = std::env::temp_dir().join(format!("groot-{}-short-names", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let long = dir.join("a rather long file name.text");
+    std::fs::write(&long, "").unwrap();
+    let fitting = dir.join("SHORT.TXT");
+    std::fs::write(&fitting, "").unwrap();
+
+    //volumes can have 8.3 names turned off, but a name that fits never gets another one
+    if let Some(short) = short_name(&long) {
+        assert!(short.contains('~'), "{short}");
+    }
+    assert_eq!(short_name(&fitting), None);
+    assert_eq!(short_name(&dir.join("missing")), None);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
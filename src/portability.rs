@@ -0,0 +1,108 @@
+use std::{io::Write, path::PathBuf};
+
+use anyhow::{bail, Result};
+
+use crate::{display_path, Directory, TreeEntry};
+
+//the longest name most filesystems take, in bytes
+const MAX_NAME_BYTES: usize = 255;
+const FORBIDDEN: &[char] = &['<', '>', ':', '"', '|', '?', '*', '\\'];
+const RESERVED: &[&str] = &["CON", "PRN", "AUX", "NUL"];
+
+/// Why `name` can't be used as-is on some OS, mostly Windows, which is the strictest.
+/// Empty for a portable name.
+fn problems(name: &str) -> Vec<String> {
+    let mut found = Vec::new();
+    if name.len() > MAX_NAME_BYTES {
+        found.push(format!(
+            "name is {} bytes, more than {MAX_NAME_BYTES}",
+            name.len()
+        ));
+    }
+    let mut forbidden: Vec<char> = name.chars().filter(|c| FORBIDDEN.contains(c)).collect();
+    forbidden.dedup();
+    if !forbidden.is_empty() {
+        let forbidden: String = forbidden.into_iter().collect();
+        found.push(format!("{forbidden} not allowed on Windows"));
+    }
+    if name.chars().any(char::is_control) {
+        found.push("control characters not allowed on Windows".to_string());
+    }
+    //reserved with any extension too, e.g. `nul.txt`
+    let stem = name.split('.').next().unwrap_or(name).trim_end();
+    let is_numbered = |prefix: &str| {
+        stem.len() == 4
+            && stem.is_ascii()
+            && stem[..3].eq_ignore_ascii_case(prefix)
+            && stem[3..].chars().all(|c| ('1'..='9').contains(&c))
+    };
+    if RESERVED
+        .iter()
+        .any(|reserved| stem.eq_ignore_ascii_case(reserved))
+        || is_numbered("COM")
+        || is_numbered("LPT")
+    {
+        found.push(format!(
+            "{} is a reserved name on Windows",
+            stem.to_uppercase()
+        ));
+    }
+    if name.ends_with('.') || name.ends_with(' ') {
+        found.push("trailing dot or space is dropped on Windows".to_string());
+    }
+    found
+}
+
+/// `--portability-check`: lists every entry whose name wouldn't survive being copied to
+/// another OS, by its path relative to the root, and fails when there is any.
+pub fn print_portability(out: &mut dyn Write, tree: &Directory) -> Result<()> {
+    let mut found = Vec::new();
+    collect(tree, PathBuf::new(), &mut found);
+    for (path, problems) in &found {
+        writeln!(out, "{}: {}", display_path(path), problems.join(", "))?;
+    }
+    if !found.is_empty() {
+        bail!("{} entries with portability problems", found.len());
+    }
+    writeln!(out, "no portability problems")?;
+    return Ok(());
+
+    fn collect(dir: &Directory, pre: PathBuf, found: &mut Vec<(PathBuf, Vec<String>)>) {
+        for entry in dir.subdirectories.iter() {
+            let path = pre.join(entry.name());
+            let problems = problems(entry.name());
+            if !problems.is_empty() {
+                found.push((path.clone(), problems));
+            }
+            if let TreeEntry::DirNode(dir_entry) = entry {
+                collect(dir_entry, path, found);
+            }
+        }
+    }
+}
+
+#[test]
+fn flags_names_other_systems_reject() {
+    assert!(problems("main.rs").is_empty());
+    assert!(problems("Console.txt").is_empty());
+    assert!(problems("COM0").is_empty());
+    assert!(problems("a\u{20ac}").is_empty());
+    assert_eq!(problems("a<b>?.txt"), vec!["<>? not allowed on Windows"]);
+    assert_eq!(
+        problems("nul.tar.gz"),
+        vec!["NUL is a reserved name on Windows"]
+    );
+    assert_eq!(problems("lpt3"), vec!["LPT3 is a reserved name on Windows"]);
+    assert_eq!(
+        problems("notes. "),
+        vec!["trailing dot or space is dropped on Windows"]
+    );
+    assert_eq!(
+        problems(&"é".repeat(128)),
+        vec!["name is 256 bytes, more than 255"]
+    );
+    assert_eq!(
+        problems("tab\there"),
+        vec!["control characters not allowed on Windows"]
+    );
+}
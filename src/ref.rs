@@ -1,32 +1,56 @@
+#[cfg(windows)]
+use std::os::windows::fs::MetadataExt;
 use std::{
-    fmt::write,
-    fs::{read_dir, DirEntry},
-    os::windows::fs::MetadataExt,
+    cmp::Reverse,
+    collections::BinaryHeap,
+    fs::DirEntry,
+    io::{self, Read, Write},
     path::{Path, PathBuf},
+    sync::mpsc,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
 type FileEntries = Option<Vec<FileEntry>>;
+/// Receives the directory that couldn't be read and why, see [`WalkerOptions::set_on_error`].
+pub type ErrorHandler = dyn Fn(&Path, &io::Error);
+/// Rewrites a name for display, see [`WalkerOptions::set_display_transform`].
+pub type NameTransform = dyn Fn(&str) -> String;
 
+#[derive(Debug)]
 pub enum WalkerError {
     MaxDepthReached,
     FileNotDirectory,
     PathNotFound,
+    //the root exists but its entries can't be listed
+    PermissionDenied(PathBuf),
+    //the walk took longer than `WalkerOptions::set_timeout` allows
+    Timeout,
 }
 
 impl std::fmt::Display for WalkerError {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "Maximum recursion limit reached for walking directories")
-    }
-}
-impl std::fmt::Debug for WalkerError {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "Maximum recursion limit reached ")
+        match self {
+            WalkerError::MaxDepthReached => {
+                write!(f, "Maximum recursion limit reached for walking directories")
+            }
+            WalkerError::FileNotDirectory => write!(f, "Path is not a directory"),
+            WalkerError::PathNotFound => write!(f, "Path does not exist"),
+            WalkerError::PermissionDenied(path) => {
+                write!(f, "Permission denied reading {}", path.display())
+            }
+            WalkerError::Timeout => write!(f, "Walk timed out, the tree is incomplete"),
+        }
     }
 }
 
 impl std::fmt::Display for FileEntry {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "{}", self.path.file_name().unwrap().to_str().unwrap())
+        //roots like `/`, `C:\` or `..` have no file name of their own
+        match self.path.file_name() {
+            Some(name) => write!(f, "{}", name.to_string_lossy()),
+            None if self.path.as_os_str().is_empty() => write!(f, "."),
+            None => write!(f, "{}", self.path.to_string_lossy()),
+        }
     }
 }
 impl std::fmt::Display for Walker {
@@ -35,17 +59,21 @@ impl std::fmt::Display for Walker {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub enum VisitStatus {
     Visited,
     Unvisited,
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct FileEntry {
     path: PathBuf,
     children: FileEntries,
     visit_status: VisitStatus,
+    //a read directory's mtime when it was read, see `Walker::rewalk_changed`
+    modified: Option<SystemTime>,
+    //entries of a read directory that were left out for being hidden
+    hidden: usize,
 }
 
 impl FileEntry {
@@ -54,14 +82,18 @@ impl FileEntry {
             path: PathBuf::new(),
             children: None,
             visit_status: VisitStatus::Unvisited,
+            modified: None,
+            hidden: 0,
         }
     }
 
-    fn from_path(path: &Path) -> Self {
+    pub fn from_path(path: &Path) -> Self {
         Self {
             path: path.to_path_buf(),
             children: None,
             visit_status: VisitStatus::Unvisited,
+            modified: None,
+            hidden: 0,
         }
     }
     fn from_dir_entry(dir_entry: &DirEntry) -> Self {
@@ -70,17 +102,19 @@ impl FileEntry {
             path: file,
             children: None,
             visit_status: VisitStatus::Unvisited,
+            modified: None,
+            hidden: 0,
         }
     }
 
-    fn get_path(&self) -> &Path {
+    pub fn get_path(&self) -> &Path {
         &self.path
     }
 
-    fn is_dir(&self) -> bool {
+    pub fn is_dir(&self) -> bool {
         self.path.is_dir()
     }
-    fn get_extension(&self) -> Option<String> {
+    pub fn get_extension(&self) -> Option<String> {
         self.path
             .extension()
             .map(|ext| ext.to_str().unwrap().to_string())
@@ -92,13 +126,13 @@ impl FileEntry {
                 let mut unit = "B";
                 let mut size = metadata.len();
                 if size > 1024 * 1024 * 1024 {
-                    size = size / (1024 * 1024 * 1024);
+                    size /= 1024 * 1024 * 1024;
                     unit = "GB"
                 } else if size > 1024 * 1024 {
-                    size = size / (1024 * 1024);
+                    size /= 1024 * 1024;
                     unit = "MB"
                 } else if size > 1024 {
-                    size = size / 1024;
+                    size /= 1024;
                     unit = "KB"
                 }
                 format!("{} {}", size, unit)
@@ -107,10 +141,22 @@ impl FileEntry {
         }
     }
 
+    /// How many entries of this directory the walk left out for being hidden.
+    pub fn hidden_count(&self) -> usize {
+        self.hidden
+    }
+
     fn visit(&mut self) {
         self.visit_status = VisitStatus::Visited;
     }
 
+    fn read_modified(&self) -> Option<SystemTime> {
+        self.path
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .ok()
+    }
+
     fn add_child(&mut self, dir_entry: FileEntry) {
         match self.children.take() {
             Some(mut dirs) => {
@@ -126,14 +172,137 @@ impl FileEntry {
     fn set_children(&mut self, dir_entries: FileEntries) {
         self.children = dir_entries;
     }
+
+    //returns whether anything below was removed
+    fn prune(&mut self, keep: &dyn Fn(&FileEntry) -> bool, drop_emptied: bool) -> bool {
+        let Some(children) = self.children.as_mut() else {
+            return false;
+        };
+        let before = children.len();
+        children.retain_mut(|child| {
+            let emptied = child.prune(keep, drop_emptied)
+                && child.children.as_ref().is_some_and(Vec::is_empty);
+            keep(child) && !(drop_emptied && emptied)
+        });
+        children.len() != before
+    }
+
+    fn total_hidden(&self) -> usize {
+        self.hidden
+            + self
+                .children
+                .iter()
+                .flatten()
+                .map(FileEntry::total_hidden)
+                .sum::<usize>()
+    }
+
+    fn depth(&self) -> usize {
+        self.children
+            .iter()
+            .flatten()
+            .map(|child| child.depth() + 1)
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// What an entry is, going by its own metadata, so a symlink is never taken for its target.
+#[cfg(feature = "petgraph")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeKind {
+    Directory,
+    File,
+    Symlink,
+    //the metadata couldn't be read, or it is something else like a socket
+    Other,
+}
+
+/// A node weight of [`Walker::to_graph`].
+#[cfg(feature = "petgraph")]
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileNode {
+    pub path: PathBuf,
+    pub kind: NodeKind,
+    //only known for files
+    pub size: Option<u64>,
+}
+
+#[cfg(feature = "petgraph")]
+impl FileNode {
+    fn of(path: &Path) -> Self {
+        let metadata = path.symlink_metadata().ok();
+        let kind = match &metadata {
+            Some(metadata) if metadata.is_symlink() => NodeKind::Symlink,
+            Some(metadata) if metadata.is_dir() => NodeKind::Directory,
+            Some(metadata) if metadata.is_file() => NodeKind::File,
+            _ => NodeKind::Other,
+        };
+        Self {
+            path: path.to_path_buf(),
+            kind,
+            size: metadata
+                .filter(|metadata| metadata.is_file())
+                .map(|metadata| metadata.len()),
+        }
+    }
 }
 
+/// What [`Walker::walk_channel`] sends along with a path and its depth. Like [`FileEntry`]s,
+/// kinds go by an entry's own metadata, so a symlink is never taken for its target.
 #[derive(Debug)]
+pub enum EntryKind {
+    Directory,
+    File,
+    Symlink,
+    //the metadata couldn't be read, or it is something else like a socket
+    Other,
+    //the directory at the path couldn't be read, in full or in part, or the walk timed out
+    //before reading it
+    Error(io::Error),
+}
+
+impl EntryKind {
+    fn of(path: &Path) -> Self {
+        match path.symlink_metadata() {
+            Ok(metadata) if metadata.is_symlink() => EntryKind::Symlink,
+            Ok(metadata) if metadata.is_dir() => EntryKind::Directory,
+            Ok(metadata) if metadata.is_file() => EntryKind::File,
+            _ => EntryKind::Other,
+        }
+    }
+}
 
 pub struct WalkerOptions {
     is_recursive: bool,
     max_depth: usize,
     show_hidden_files: bool,
+    //follows show_hidden_files unless it was set on its own
+    recurse_hidden_dirs: Option<bool>,
+    prune_empty_dirs: bool,
+    max_link_depth: usize,
+    timeout: Option<Duration>,
+    on_error: Option<Box<ErrorHandler>>,
+    display_transform: Option<Box<NameTransform>>,
+}
+
+impl std::fmt::Debug for WalkerOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("WalkerOptions")
+            .field("is_recursive", &self.is_recursive)
+            .field("max_depth", &self.max_depth)
+            .field("show_hidden_files", &self.show_hidden_files)
+            .field("recurse_hidden_dirs", &self.recurse_hidden_dirs)
+            .field("prune_empty_dirs", &self.prune_empty_dirs)
+            .field("max_link_depth", &self.max_link_depth)
+            .field("timeout", &self.timeout)
+            .field("on_error", &self.on_error.as_ref().map(|_| "Fn"))
+            .field(
+                "display_transform",
+                &self.display_transform.as_ref().map(|_| "Fn"),
+            )
+            .finish()
+    }
 }
 
 impl WalkerOptions {
@@ -141,10 +310,82 @@ impl WalkerOptions {
         self.is_recursive = recursive;
         self
     }
+    /// Whether hidden entries are listed. A hidden directory that isn't recursed into
+    /// is then listed without its contents.
     pub fn set_show_hidden_files(mut self, show: bool) -> Self {
         self.show_hidden_files = show;
         self
     }
+    /// Whether the walk descends into hidden directories. Recursed directories are always
+    /// listed so their contents have a parent, even when hidden files aren't shown. Unless this
+    /// is called, hidden directories are walked exactly when hidden files are shown.
+    pub fn set_recurse_hidden_dirs(mut self, recurse: bool) -> Self {
+        self.recurse_hidden_dirs = Some(recurse);
+        self
+    }
+
+    /// Whether [`Walker::prune`] also drops directories it left without any entries.
+    pub fn set_prune_empty_dirs(mut self, prune: bool) -> Self {
+        self.prune_empty_dirs = prune;
+        self
+    }
+
+    /// How many symlinked directories the walk follows along any one path, on top of the
+    /// `max_depth` limit on directories. A symlinked directory past the limit is listed without
+    /// its contents. Unlimited by default.
+    pub fn set_max_link_depth(mut self, max_link_depth: usize) -> Self {
+        self.max_link_depth = max_link_depth;
+        self
+    }
+
+    /// How long [`Walker::walk_from_root`] may take. Past it the walk stops before reading the
+    /// next directory and keeps what it has so far.
+    pub fn set_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Called with the directory and the error whenever a directory, or an entry in it, can't be
+    /// read. Without one the error is printed to stderr.
+    pub fn set_on_error(mut self, on_error: impl Fn(&Path, &io::Error) + 'static) -> Self {
+        self.on_error = Some(Box::new(on_error));
+        self
+    }
+
+    /// Applied to every name [`Walker::print`] shows, e.g. to mask or transliterate them.
+    /// The walk and its filters still see the real names.
+    pub fn set_display_transform(mut self, transform: impl Fn(&str) -> String + 'static) -> Self {
+        self.display_transform = Some(Box::new(transform));
+        self
+    }
+
+    //the name as printed
+    fn display_name(&self, name: &str) -> String {
+        match &self.display_transform {
+            Some(transform) => transform(name),
+            None => name.to_string(),
+        }
+    }
+
+    fn recurses_hidden_dirs(&self) -> bool {
+        self.recurse_hidden_dirs.unwrap_or(self.show_hidden_files)
+    }
+
+    fn keeps(&self, entry: &DirEntry) -> bool {
+        self.show_hidden_files
+            || !file_is_hidden(entry)
+            || (self.recurses_hidden_dirs() && entry.path().is_dir())
+    }
+
+    fn descends_into(&self, dir: &DirEntry) -> bool {
+        self.recurses_hidden_dirs() || !file_is_hidden(dir)
+    }
+
+    //the link hops below `dir` when the walk may still go into it
+    fn link_depth_into(&self, dir: &Path, links: usize) -> Option<usize> {
+        let links = links + usize::from(dir.is_symlink());
+        (links <= self.max_link_depth).then_some(links)
+    }
 }
 
 impl WalkerOptions {
@@ -153,14 +394,28 @@ impl WalkerOptions {
             is_recursive: false,
             max_depth: u8::MAX as usize,
             show_hidden_files: true,
+            recurse_hidden_dirs: None,
+            prune_empty_dirs: false,
+            max_link_depth: usize::MAX,
+            timeout: None,
+            on_error: None,
+            display_transform: None,
         }
     }
 }
 
+impl Default for WalkerOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[derive(Debug)]
 pub struct Walker {
     options: WalkerOptions,
     root: FileEntry,
+    //when the running walk has to stop, from the timeout option
+    deadline: Option<Instant>,
 }
 
 impl Walker {
@@ -169,17 +424,47 @@ impl Walker {
             return Err(WalkerError::PathNotFound);
         }
         let root = FileEntry::from_path(root_path);
-        // let unvisited_dir_entries = get_dir_entries(root_path);
-        // for dir_entry in unvisited_dir_entries {
-        //     let file_entry = FileEntry::from_dir_entry(dir_entry);
-        //     root.add_child(file_entry);
-        // }
 
-        Ok(Self { root, options })
+        Ok(Self {
+            root,
+            options,
+            deadline: None,
+        })
+    }
+
+    /// Builds a walker around entries that were already gathered, e.g. a filtered result of
+    /// [`list_dir`] or entries made with [`FileEntry::from_path`]. Nothing is read from disk.
+    pub fn from_entries(root: PathBuf, children: Vec<FileEntry>) -> Self {
+        let mut root = FileEntry::from_path(&root);
+        root.visit();
+        root.set_children(Some(children));
+
+        Self {
+            root,
+            options: WalkerOptions::new(),
+            deadline: None,
+        }
+    }
+
+    /// The path the walker was made for, as it was given, to tell which tree results came from.
+    pub fn root_path(&self) -> &Path {
+        self.root.get_path()
     }
 
-    pub fn walk_from_root(&mut self) {
+    /// Reads the tree below the root. Fails with [`WalkerError::PermissionDenied`] before
+    /// reading anything when the root directory can't be listed, and with
+    /// [`WalkerError::Timeout`], in which case the entries read until then are kept. Directories
+    /// below the root that can't be read are only reported, see [`WalkerOptions::set_on_error`].
+    pub fn walk_from_root(&mut self) -> Result<(), WalkerError> {
         let depth = 0;
+        if self.root.is_dir() {
+            if let Err(err) = std::fs::read_dir(&self.root.path) {
+                if err.kind() == io::ErrorKind::PermissionDenied {
+                    return Err(WalkerError::PermissionDenied(self.root.path.clone()));
+                }
+            }
+        }
+        self.deadline = self.options.timeout.map(|timeout| Instant::now() + timeout);
 
         if self.options.is_recursive {
             //take a parent dir
@@ -187,25 +472,38 @@ impl Walker {
             //get all entries
             //self.root=>self.root.children=>
             let mut root = std::mem::replace(&mut self.root, FileEntry::new());
-            self.walk_dir_recursive(&mut root, depth);
+            let walked = self.walk_dir_recursive(&mut root, depth, 0);
             self.root = root;
+            if let Err(WalkerError::Timeout) = walked {
+                return walked;
+            }
         } else {
             self.root.visit();
+            self.root.modified = self.root.read_modified();
 
             match self.walk_dir(&self.root, depth) {
-                Ok(entries) => {
+                Ok((entries, hidden)) => {
                     self.root.set_children(entries);
+                    self.root.hidden = hidden;
                 }
                 Err(e) => match e {
                     WalkerError::MaxDepthReached => (),
                     WalkerError::FileNotDirectory => (),
                     WalkerError::PathNotFound => (),
+                    WalkerError::PermissionDenied(_) | WalkerError::Timeout => return Err(e),
                 },
             }
         }
+        Ok(())
     }
 
-    fn walk_dir_recursive(&self, parent: &mut FileEntry, depth: usize) -> Result<(), WalkerError> {
+    //`links` counts the symlinked directories followed on the way to `parent`
+    fn walk_dir_recursive(
+        &self,
+        parent: &mut FileEntry,
+        depth: usize,
+        links: usize,
+    ) -> Result<(), WalkerError> {
         parent.visit();
         let depth = depth + 1;
         if depth == self.options.max_depth {
@@ -217,67 +515,348 @@ impl Walker {
 
             return Err(WalkerError::FileNotDirectory);
         }
+        if self
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            return Err(WalkerError::Timeout);
+        }
+
+        parent.modified = parent.read_modified();
+        let dir_entries = get_dir_entries(parent.get_path(), self.options.on_error.as_deref());
+
+        for entry in dir_entries {
+            if !self.options.keeps(&entry) {
+                parent.hidden += 1;
+                continue;
+            }
+            let mut child = FileEntry::from_dir_entry(&entry);
+            if entry.path().is_dir() && self.options.descends_into(&entry) {
+                if let Some(links) = self.options.link_depth_into(&entry.path(), links) {
+                    if let Err(WalkerError::Timeout) =
+                        self.walk_dir_recursive(&mut child, depth, links)
+                    {
+                        parent.add_child(child);
+                        return Err(WalkerError::Timeout);
+                    }
+                }
+            }
+            parent.add_child(child);
+        }
+        Ok(())
+    }
+
+    //the entries kept and how many were left out as hidden
+    fn walk_dir(
+        &self,
+        parent: &FileEntry,
+        depth: usize,
+    ) -> Result<(FileEntries, usize), WalkerError> {
+        if depth == self.options.max_depth {
+            return Err(WalkerError::MaxDepthReached);
+        }
+
+        if !parent.is_dir() {
+            return Err(WalkerError::FileNotDirectory);
+        }
 
-        let dir_entries = get_dir_entries(parent.get_path());
+        let dir_entries = get_dir_entries(parent.get_path(), self.options.on_error.as_deref());
 
-        if self.options.show_hidden_files {
-            // println!("Not a hidden Parent: {:#?}", parent);
+        let total = dir_entries.len();
+        let entries = dir_entries
+            .into_iter()
+            .filter(|dir_entry| self.options.keeps(dir_entry))
+            .map(|dir_entry| Some(FileEntry::from_dir_entry(&dir_entry)))
+            .collect::<FileEntries>();
+        let hidden = total - entries.as_ref().map_or(0, Vec::len);
+        Ok((entries, hidden))
+    }
+    /// Brings an earlier walk up to date by re-reading only the directories whose mtime changed
+    /// since they were read. Entries that are still there keep their walked subtree, new
+    /// directories are walked in full and unchanged directories aren't read at all, though
+    /// every walked directory is still checked.
+    ///
+    /// A directory's mtime only changes when entries are added, removed or renamed in it, so
+    /// files whose contents changed in place aren't noticed.
+    pub fn rewalk_changed(&mut self) {
+        //only the initial walk is timed
+        self.deadline = None;
+        let mut root = std::mem::replace(&mut self.root, FileEntry::new());
+        self.rewalk_dir(&mut root, 0, 0);
+        self.root = root;
+    }
+
+    fn rewalk_dir(&self, dir: &mut FileEntry, depth: usize, links: usize) {
+        let depth = depth + 1;
+        if depth == self.options.max_depth {
+            return;
+        }
 
-            for entry in dir_entries {
-                let mut child = FileEntry::from_dir_entry(&entry);
-                if entry.path().is_dir() {
-                    let _ = self.walk_dir_recursive(&mut child, depth);
+        let modified = dir.read_modified();
+        if modified != dir.modified {
+            dir.modified = modified;
+            let mut previous = dir.children.take().unwrap_or_default();
+            let mut children = Vec::new();
+            dir.hidden = 0;
+            for entry in get_dir_entries(dir.get_path(), self.options.on_error.as_deref()) {
+                if !self.options.keeps(&entry) {
+                    dir.hidden += 1;
+                    continue;
+                }
+                let path = entry.path();
+                match previous.iter().position(|child| child.path == path) {
+                    Some(i) => children.push(previous.swap_remove(i)),
+                    None => {
+                        let mut child = FileEntry::from_dir_entry(&entry);
+                        if self.options.is_recursive
+                            && path.is_dir()
+                            && self.options.descends_into(&entry)
+                        {
+                            if let Some(links) = self.options.link_depth_into(&path, links) {
+                                let _ = self.walk_dir_recursive(&mut child, depth, links);
+                            }
+                        }
+                        children.push(child);
+                    }
                 }
-                parent.add_child(child);
             }
-            Ok(())
-        } else {
-            // println!("Is a hiddenFile: {:#?}", parent);
+            dir.set_children(Some(children));
+        }
 
-            for entry in dir_entries {
-                if file_is_hidden(&entry) {
+        if self.options.is_recursive {
+            //only directories that were read have an mtime to compare against
+            for child in dir.children.iter_mut().flatten() {
+                if child.modified.is_none() {
                     continue;
                 }
-                let mut child = FileEntry::from_dir_entry(&entry);
-                if entry.path().is_dir() {
-                    let _ = self.walk_dir_recursive(&mut child, depth);
+                if let Some(links) = self.options.link_depth_into(&child.path, links) {
+                    self.rewalk_dir(child, depth, links);
                 }
-                parent.add_child(child);
             }
-            Ok(())
         }
     }
 
-    fn walk_dir(&self, parent: &FileEntry, depth: usize) -> Result<FileEntries, WalkerError> {
-        if depth == self.options.max_depth {
-            return Err(WalkerError::MaxDepthReached);
+    /// How many levels below the root the walked tree actually goes: 0 for a root without
+    /// children, 1 when only its direct entries were read. Unlike the `max_depth` option this
+    /// is measured on what was materialized.
+    pub fn max_depth_reached(&self) -> usize {
+        self.root.depth()
+    }
+
+    /// How many hidden entries the walk left out across the whole tree, see
+    /// [`FileEntry::hidden_count`] for a single directory.
+    pub fn hidden_count(&self) -> usize {
+        self.root.total_hidden()
+    }
+
+    /// The walked tree as a directed graph with an edge from every directory to each of its
+    /// entries. The root is the first node, and nodes are added in pre-order.
+    #[cfg(feature = "petgraph")]
+    pub fn to_graph(&self) -> petgraph::Graph<FileNode, ()> {
+        let mut graph = petgraph::Graph::new();
+        let root = graph.add_node(FileNode::of(&self.root.path));
+        add_children(&mut graph, root, &self.root);
+        return graph;
+
+        fn add_children(
+            graph: &mut petgraph::Graph<FileNode, ()>,
+            parent: petgraph::graph::NodeIndex,
+            entry: &FileEntry,
+        ) {
+            for child in entry.children.iter().flatten() {
+                let node = graph.add_node(FileNode::of(&child.path));
+                graph.add_edge(parent, node, ());
+                add_children(graph, node, child);
+            }
+        }
+    }
+
+    /// The `n` biggest files anywhere in the walked tree, largest first. Only `n` of them are
+    /// held while looking, so the rest of the tree is never sorted. Symlinks and entries whose
+    /// metadata can't be read don't count, and files of the same size keep their walk order.
+    pub fn largest_files(&self, n: usize) -> Vec<&FileEntry> {
+        //a min-heap on (size, earlier in the walk), so the smallest of the kept files is on top
+        let mut heap = BinaryHeap::with_capacity(n + 1);
+        let mut stack = vec![&self.root];
+        let mut order = 0;
+        while let Some(entry) = stack.pop() {
+            let children = entry.children.iter().flatten().rev();
+            stack.extend(children);
+            let Ok(metadata) = std::fs::symlink_metadata(&entry.path) else {
+                continue;
+            };
+            if !metadata.is_file() || n == 0 {
+                continue;
+            }
+            order += 1;
+            heap.push(Candidate {
+                key: (Reverse(metadata.len()), order),
+                entry,
+            });
+            if heap.len() > n {
+                heap.pop();
+            }
         }
+        return heap
+            .into_sorted_vec()
+            .into_iter()
+            .map(|candidate| candidate.entry)
+            .collect();
 
-        if !parent.is_dir() {
-            return Err(WalkerError::FileNotDirectory);
+        //ordered by key alone, entries themselves aren't comparable
+        struct Candidate<'a> {
+            key: (Reverse<u64>, usize),
+            entry: &'a FileEntry,
+        }
+        impl PartialEq for Candidate<'_> {
+            fn eq(&self, other: &Self) -> bool {
+                self.key == other.key
+            }
+        }
+        impl Eq for Candidate<'_> {}
+        impl PartialOrd for Candidate<'_> {
+            fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+        impl Ord for Candidate<'_> {
+            fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+                self.key.cmp(&other.key)
+            }
         }
+    }
 
-        let dir_entries = get_dir_entries(parent.get_path());
+    /// Projects every materialized entry, the root first and then in pre-order, into whatever
+    /// `f` returns in one pass over the tree, keeping only the `Some` results. This is the way
+    /// to compute a custom column, like a size, a digest or a VCS status, next to each entry:
+    ///
+    /// ```
+    /// use std::path::Path;
+    ///
+    /// use groot::r#ref::{Walker, WalkerOptions};
+    ///
+    /// let mut walker = Walker::from_path(Path::new("."), WalkerOptions::new()).unwrap();
+    /// walker.walk_from_root().unwrap();
+    /// //a size column for the files, leaving the directories out
+    /// let rows: Vec<String> = walker.map_entries(|entry| {
+    ///     let metadata = entry.get_path().symlink_metadata().ok()?;
+    ///     metadata
+    ///         .is_file()
+    ///         .then(|| format!("{:>10}  {entry}", metadata.len()))
+    /// });
+    /// assert!(rows.iter().any(|row| row.ends_with("  Cargo.toml")));
+    /// ```
+    pub fn map_entries<T, F: Fn(&FileEntry) -> Option<T>>(&self, f: F) -> Vec<T> {
+        let mut mapped = Vec::new();
+        let mut stack = vec![&self.root];
+        while let Some(entry) = stack.pop() {
+            //reversed so the first child comes off the stack first
+            stack.extend(entry.children.iter().flatten().rev());
+            mapped.extend(f(entry));
+        }
+        mapped
+    }
 
-        if self.options.show_hidden_files {
-            Ok(dir_entries
-                .into_iter()
-                .map(|dir_entry| Some(FileEntry::from_dir_entry(&dir_entry)))
-                .collect::<FileEntries>())
-        } else {
-            Ok(dir_entries
-                .into_iter()
-                .filter(|dir_entry| !file_is_hidden(dir_entry))
-                .map(|dir_entry| Some(FileEntry::from_dir_entry(&dir_entry)))
-                .collect::<FileEntries>())
+    /// The path and depth of every materialized entry in pre-order, starting with the root at
+    /// depth 0, without handing out the entries themselves.
+    pub fn paths(&self) -> impl Iterator<Item = (PathBuf, usize)> + '_ {
+        let mut stack = vec![(&self.root, 0)];
+        std::iter::from_fn(move || {
+            let (entry, depth) = stack.pop()?;
+            //reversed so the first child comes off the stack first
+            let children = entry.children.iter().flatten().rev();
+            stack.extend(children.map(|child| (child, depth + 1)));
+            Some((entry.path.clone(), depth))
+        })
+    }
+
+    /// Walks on a background thread instead, sending every entry through the channel as soon as
+    /// its directory is read, so a consumer can work on entries while the walk goes on. Each
+    /// entry comes with its depth, the root first at depth 0 and the rest in pre-order, and its
+    /// [`EntryKind`]. A directory that can't be read is sent again as an
+    /// [`EntryKind::Error`] with the reason, as is the directory the walk stopped at when the
+    /// timeout runs out. Nothing is kept in the walker.
+    ///
+    /// The thread ends once the walk is done, which closes the channel, or as soon as the
+    /// receiver is dropped. The `on_error` and display callbacks aren't used.
+    pub fn walk_channel(self) -> mpsc::Receiver<(PathBuf, usize, EntryKind)> {
+        let (sender, receiver) = mpsc::channel();
+        //the callbacks may not be sent to another thread, so the options are rebuilt without them
+        let WalkerOptions {
+            is_recursive,
+            max_depth,
+            show_hidden_files,
+            recurse_hidden_dirs,
+            prune_empty_dirs,
+            max_link_depth,
+            timeout,
+            ..
+        } = self.options;
+        let root = self.root.path;
+        std::thread::spawn(move || {
+            let options = WalkerOptions {
+                is_recursive,
+                max_depth,
+                show_hidden_files,
+                recurse_hidden_dirs,
+                prune_empty_dirs,
+                max_link_depth,
+                timeout,
+                on_error: None,
+                display_transform: None,
+            };
+            let walk = ChannelWalk {
+                deadline: options.timeout.map(|timeout| Instant::now() + timeout),
+                options,
+                sender,
+            };
+            //an error only means the walk stopped early
+            let _ = walk.send_tree(&root);
+        });
+        receiver
+    }
+
+    /// Removes every walked entry for which `keep` returns false, along with everything below it.
+    /// With [`WalkerOptions::set_prune_empty_dirs`], directories that are left empty by this
+    /// are removed too; directories that were empty to begin with stay.
+    pub fn prune<F: Fn(&FileEntry) -> bool>(&mut self, keep: F) {
+        self.root.prune(&keep, self.options.prune_empty_dirs);
+    }
+
+    /// Saves the walked tree in a compact binary form that [`Walker::read_binary`] loads again,
+    /// for tools that cache huge trees. Options aren't saved. Paths that aren't valid UTF-8
+    /// can't be written.
+    pub fn write_binary<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        w.write_all(BINARY_MAGIC)?;
+        write_entry(w, &self.root, None)
+    }
+
+    /// Loads a tree saved with [`Walker::write_binary`], with default options.
+    pub fn read_binary<R: Read>(r: &mut R) -> io::Result<Self> {
+        let mut magic = [0; 4];
+        r.read_exact(&mut magic)?;
+        if magic != *BINARY_MAGIC {
+            return Err(invalid_data("not a saved tree"));
         }
+        Ok(Self {
+            root: read_entry(r, None)?,
+            options: WalkerOptions::new(),
+            deadline: None,
+        })
     }
+
     pub fn print(&self) {
-        println!("{}:", self.root.path.as_os_str().to_str().unwrap());
+        let _ = self.write_listing(&mut io::stdout().lock());
+    }
+
+    fn write_listing(&self, out: &mut dyn Write) -> io::Result<()> {
+        let root = self.root.path.as_os_str().to_str().unwrap();
+        writeln!(out, "{}:", self.options.display_name(root))?;
         match &self.root.children {
             Some(entries) => {
                 for entry in entries.iter() {
-                    println!(
+                    writeln!(
+                        out,
                         "[{}]\t{} \t{}",
                         if entry.is_dir() {
                             "DIR".to_string()
@@ -287,20 +866,112 @@ impl Walker {
                                 None => String::from("N/A"),
                             }
                         },
-                        entry,
+                        self.options.display_name(&entry.to_string()),
                         entry.get_size()
-                    );
+                    )?;
                     // println!("{}", entry);
                 }
             }
             None => {
-                println!("THERE ARE NO FILES INSIDE");
+                writeln!(out, "THERE ARE NO FILES INSIDE")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+//the walk behind `Walker::walk_channel`, on its own thread
+struct ChannelWalk {
+    options: WalkerOptions,
+    deadline: Option<Instant>,
+    sender: mpsc::Sender<(PathBuf, usize, EntryKind)>,
+}
+
+impl ChannelWalk {
+    //fails once the walk has to stop, because the receiver is gone or time ran out
+    fn send(&self, path: &Path, depth: usize, kind: EntryKind) -> Result<(), ()> {
+        self.sender
+            .send((path.to_path_buf(), depth, kind))
+            .map_err(|_| ())
+    }
+
+    fn send_tree(&self, root: &Path) -> Result<(), ()> {
+        self.send(root, 0, EntryKind::of(root))?;
+        if root.is_dir() {
+            self.send_dir(root, 0, 0)?;
+        }
+        Ok(())
+    }
+
+    //`dir` is at `depth`, and `links` counts the symlinked directories followed to get there
+    fn send_dir(&self, dir: &Path, depth: usize, links: usize) -> Result<(), ()> {
+        //the same limit the other walks have, which counts the root as a level when recursing
+        if depth + usize::from(self.options.is_recursive) >= self.options.max_depth {
+            return Ok(());
+        }
+        if self
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            let timed_out =
+                io::Error::new(io::ErrorKind::TimedOut, WalkerError::Timeout.to_string());
+            self.send(dir, depth, EntryKind::Error(timed_out))?;
+            return Err(());
+        }
+
+        let read_dir = match std::fs::read_dir(dir) {
+            Ok(read_dir) => read_dir,
+            Err(err) => return self.send(dir, depth, EntryKind::Error(err)),
+        };
+        for entry in read_dir {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(err) => {
+                    self.send(dir, depth, EntryKind::Error(err))?;
+                    continue;
+                }
+            };
+            if !self.options.keeps(&entry) {
+                continue;
+            }
+            let path = entry.path();
+            self.send(&path, depth + 1, EntryKind::of(&path))?;
+            if self.options.is_recursive && path.is_dir() && self.options.descends_into(&entry) {
+                if let Some(links) = self.options.link_depth_into(&path, links) {
+                    self.send_dir(&path, depth + 1, links)?;
+                }
             }
         }
+        Ok(())
     }
 }
 
-fn get_dir_entries(path: &Path) -> Vec<DirEntry> {
+/// Reads the entries of a single directory without walking into any of its subdirectories.
+pub fn list_dir(path: &Path, show_hidden: bool) -> Result<Vec<FileEntry>, WalkerError> {
+    if !path.exists() {
+        return Err(WalkerError::PathNotFound);
+    }
+    if !path.is_dir() {
+        return Err(WalkerError::FileNotDirectory);
+    }
+
+    Ok(get_dir_entries(path, None)
+        .into_iter()
+        .filter(|dir_entry| show_hidden || !file_is_hidden(dir_entry))
+        .map(|dir_entry| FileEntry::from_dir_entry(&dir_entry))
+        .collect())
+}
+
+fn get_dir_entries(path: &Path, on_error: Option<&ErrorHandler>) -> Vec<DirEntry> {
+    let report = |err: io::Error| match on_error {
+        Some(on_error) => on_error(path, &err),
+        None => eprintln!(
+            "ERROR [{}]: Cannot read Directory of path: {}",
+            err,
+            path.to_str().unwrap()
+        ),
+    };
+
     let mut dirs = Vec::new();
     match std::fs::read_dir(path) {
         Ok(read_dir) => {
@@ -309,34 +980,167 @@ fn get_dir_entries(path: &Path) -> Vec<DirEntry> {
                     Ok(f) => {
                         dirs.push(f);
                     }
-                    Err(err) => {
-                        eprintln!(
-                            "ERROR [{}]: Cannot read Directory of path: {}",
-                            err,
-                            path.to_str().unwrap()
-                        )
-                    }
+                    Err(err) => report(err),
                 }
             }
         }
-        Err(err) => eprintln!(
-            "ERROR [{}]: Cannot read Directory of path: {}",
-            err,
-            path.to_str().unwrap()
-        ),
+        Err(err) => report(err),
     }
-    return dirs;
+    dirs
 }
 
-fn file_is_hidden(file: &DirEntry) -> bool {
-    const FILE_ATTRIBUTE_HIDDEN: u32 = 0x02;
-    let file_attr = match file.metadata() {
-        Ok(metadata) => metadata.file_attributes(),
-        Err(_) => return false,
+//the binary format: the magic, then the root entry. Each entry is a flags byte, its path as a
+//u32 length and UTF-8 bytes, relative to its parent when it is below it, the mtime as i64
+//seconds and u32 nanoseconds when there is one, the u64 count of hidden entries left out when
+//there were any, and a u32 count followed by the children when they were read. Integers are
+//little-endian.
+const BINARY_MAGIC: &[u8; 4] = b"GRT1";
+const HAS_CHILDREN: u8 = 1;
+const VISITED: u8 = 1 << 1;
+const HAS_MODIFIED: u8 = 1 << 2;
+const RELATIVE_PATH: u8 = 1 << 3;
+const HAS_HIDDEN: u8 = 1 << 4;
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn write_entry<W: Write>(w: &mut W, entry: &FileEntry, parent: Option<&Path>) -> io::Result<()> {
+    let relative = parent.and_then(|parent| entry.path.strip_prefix(parent).ok());
+    let mut flags = 0;
+    if entry.children.is_some() {
+        flags |= HAS_CHILDREN;
+    }
+    if matches!(entry.visit_status, VisitStatus::Visited) {
+        flags |= VISITED;
+    }
+    if entry.modified.is_some() {
+        flags |= HAS_MODIFIED;
+    }
+    if relative.is_some() {
+        flags |= RELATIVE_PATH;
+    }
+    if entry.hidden > 0 {
+        flags |= HAS_HIDDEN;
+    }
+    w.write_all(&[flags])?;
+
+    let path = relative.unwrap_or(&entry.path);
+    let path = path
+        .to_str()
+        .ok_or_else(|| invalid_data("path is not valid UTF-8"))?;
+    let len = u32::try_from(path.len()).map_err(|_| invalid_data("path is too long"))?;
+    w.write_all(&len.to_le_bytes())?;
+    w.write_all(path.as_bytes())?;
+
+    if let Some(modified) = entry.modified {
+        let (secs, nanos) = match modified.duration_since(UNIX_EPOCH) {
+            Ok(since) => (since.as_secs() as i64, since.subsec_nanos()),
+            //whole seconds before the epoch, then forward by the nanoseconds
+            Err(before) => {
+                let before = before.duration();
+                let secs = -(before.as_secs() as i64) - i64::from(before.subsec_nanos() > 0);
+                (
+                    secs,
+                    (1_000_000_000 - before.subsec_nanos()) % 1_000_000_000,
+                )
+            }
+        };
+        w.write_all(&secs.to_le_bytes())?;
+        w.write_all(&nanos.to_le_bytes())?;
+    }
+    if entry.hidden > 0 {
+        w.write_all(&(entry.hidden as u64).to_le_bytes())?;
+    }
+
+    if let Some(children) = &entry.children {
+        let count = u32::try_from(children.len()).map_err(|_| invalid_data("too many entries"))?;
+        w.write_all(&count.to_le_bytes())?;
+        for child in children {
+            write_entry(w, child, Some(&entry.path))?;
+        }
+    }
+    Ok(())
+}
+
+fn read_entry<R: Read>(r: &mut R, parent: Option<&Path>) -> io::Result<FileEntry> {
+    fn read_array<R: Read, const N: usize>(r: &mut R) -> io::Result<[u8; N]> {
+        let mut bytes = [0; N];
+        r.read_exact(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    let [flags] = read_array(r)?;
+    let len = u32::from_le_bytes(read_array(r)?);
+    let mut path = Vec::new();
+    r.take(u64::from(len)).read_to_end(&mut path)?;
+    if path.len() != len as usize {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    let path = PathBuf::from(String::from_utf8(path).map_err(|_| invalid_data("bad path"))?);
+    let path = match parent {
+        Some(parent) if flags & RELATIVE_PATH != 0 => parent.join(path),
+        _ => path,
+    };
+
+    let modified = if flags & HAS_MODIFIED != 0 {
+        let secs = i64::from_le_bytes(read_array(r)?);
+        let nanos = u32::from_le_bytes(read_array(r)?);
+        let since = Duration::from_secs(secs.unsigned_abs());
+        let time = if secs >= 0 {
+            UNIX_EPOCH.checked_add(since)
+        } else {
+            UNIX_EPOCH.checked_sub(since)
+        };
+        time.and_then(|time| time.checked_add(Duration::from_nanos(nanos.into())))
+    } else {
+        None
     };
-    //FILE_ATTRIBUTE_HIDDEN is 0x02 for windows and
-    //any number that results in a number greater than zero after bitwise-and with it is hidden
-    return file_attr & FILE_ATTRIBUTE_HIDDEN != 0;
+    let hidden = if flags & HAS_HIDDEN != 0 {
+        usize::try_from(u64::from_le_bytes(read_array(r)?))
+            .map_err(|_| invalid_data("bad hidden count"))?
+    } else {
+        0
+    };
+
+    let children = if flags & HAS_CHILDREN != 0 {
+        let count = u32::from_le_bytes(read_array(r)?);
+        //not preallocated, the count isn't trusted until the entries are there
+        let mut children = Vec::new();
+        for _ in 0..count {
+            children.push(read_entry(r, Some(&path))?);
+        }
+        Some(children)
+    } else {
+        None
+    };
+
+    Ok(FileEntry {
+        path,
+        children,
+        visit_status: if flags & VISITED != 0 {
+            VisitStatus::Visited
+        } else {
+            VisitStatus::Unvisited
+        },
+        modified,
+        hidden,
+    })
+}
+
+fn file_is_hidden(file: &DirEntry) -> bool {
+    if file.file_name().to_string_lossy().starts_with('.') {
+        return true;
+    }
+    #[cfg(windows)]
+    if let Ok(metadata) = file.metadata() {
+        const FILE_ATTRIBUTE_HIDDEN: u32 = 0x02;
+        let file_attr = metadata.file_attributes();
+        //FILE_ATTRIBUTE_HIDDEN is 0x02 for windows and
+        //any number that results in a number greater than zero after bitwise-and with it is hidden
+        return file_attr & FILE_ATTRIBUTE_HIDDEN != 0;
+    }
+    false
 }
 
 // read a dir
@@ -361,10 +1165,595 @@ fn add_child() {
     let child_file = FileEntry::new();
     file.add_child(child_file);
     let second_child = FileEntry::new();
-    let mut second_file = FileEntry {
+    let second_file = FileEntry {
         path: PathBuf::from("foo.txt"),
         children: Some(vec![second_child]),
         visit_status: VisitStatus::Unvisited,
+        modified: None,
+        hidden: 0,
     };
+    assert_eq!(
+        file.children.as_ref().map(Vec::len),
+        second_file.children.as_ref().map(Vec::len)
+    );
     dbg!(file);
 }
+
+//creates an empty scratch directory unique to the calling test
+#[cfg(test)]
+fn test_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("groot-{}-{}", std::process::id(), name));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+//every entry below the walker's root as a `/`-separated relative path, in pre-order
+#[cfg(test)]
+fn walked_paths(walker: &Walker) -> Vec<String> {
+    fn visit(entry: &FileEntry, pre: &str, out: &mut Vec<String>) {
+        for child in entry.children.iter().flatten() {
+            let path = format!("{pre}{child}");
+            out.push(path.clone());
+            visit(child, &format!("{path}/"), out);
+        }
+    }
+    let mut out = Vec::new();
+    visit(&walker.root, "", &mut out);
+    out
+}
+
+#[test]
+fn list_dir_reads_one_level() {
+    let dir = test_dir("list_dir");
+    std::fs::write(dir.join("a.txt"), "a").unwrap();
+    std::fs::write(dir.join(".hidden"), "").unwrap();
+    std::fs::create_dir(dir.join("sub")).unwrap();
+    std::fs::write(dir.join("sub").join("inner.txt"), "").unwrap();
+
+    let names = |entries: Vec<FileEntry>| {
+        let mut names: Vec<String> = entries.iter().map(|entry| entry.to_string()).collect();
+        names.sort();
+        names
+    };
+    assert_eq!(names(list_dir(&dir, false).unwrap()), vec!["a.txt", "sub"]);
+    assert_eq!(
+        names(list_dir(&dir, true).unwrap()),
+        vec![".hidden", "a.txt", "sub"]
+    );
+    assert!(matches!(
+        list_dir(&dir.join("a.txt"), true),
+        Err(WalkerError::FileNotDirectory)
+    ));
+    assert!(matches!(
+        list_dir(&dir.join("missing"), true),
+        Err(WalkerError::PathNotFound)
+    ));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn from_entries_keeps_given_children() {
+    let children = vec![
+        FileEntry::from_path(Path::new("project/src")),
+        FileEntry::from_path(Path::new("project/Cargo.toml")),
+    ];
+    let walker = Walker::from_entries(PathBuf::from("project"), children);
+
+    assert_eq!(walker.to_string(), "project");
+    assert_eq!(walker.root_path(), Path::new("project"));
+    let names: Vec<String> = walker
+        .root
+        .children
+        .iter()
+        .flatten()
+        .map(|entry| entry.to_string())
+        .collect();
+    assert_eq!(names, vec!["src", "Cargo.toml"]);
+}
+
+#[test]
+fn hidden_files_and_dirs_are_independent() {
+    let dir = test_dir("hidden_combinations");
+    std::fs::write(dir.join("visible.txt"), "").unwrap();
+    std::fs::write(dir.join(".hidden.txt"), "").unwrap();
+    std::fs::create_dir(dir.join(".config")).unwrap();
+    std::fs::write(dir.join(".config").join("inner.txt"), "").unwrap();
+
+    let walk = |show: bool, recurse: bool| {
+        let options = WalkerOptions::new()
+            .set_recursive(true)
+            .set_show_hidden_files(show)
+            .set_recurse_hidden_dirs(recurse);
+        let mut walker = Walker::from_path(&dir, options).unwrap();
+        walker.walk_from_root().unwrap();
+
+        let mut names = Vec::new();
+        for entry in walker.root.children.iter().flatten() {
+            names.push(entry.to_string());
+            for child in entry.children.iter().flatten() {
+                names.push(format!("{}/{}", entry, child));
+            }
+        }
+        names.sort();
+        names
+    };
+
+    assert_eq!(walk(false, false), vec!["visible.txt"]);
+    assert_eq!(
+        walk(true, false),
+        vec![".config", ".hidden.txt", "visible.txt"]
+    );
+    assert_eq!(
+        walk(false, true),
+        vec![".config", ".config/inner.txt", "visible.txt"]
+    );
+    assert_eq!(
+        walk(true, true),
+        vec![".config", ".config/inner.txt", ".hidden.txt", "visible.txt"]
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn hiding_files_hides_dirs_by_default() {
+    let dir = test_dir("hidden_default");
+    std::fs::write(dir.join("visible.txt"), "").unwrap();
+    std::fs::create_dir(dir.join(".git")).unwrap();
+    std::fs::write(dir.join(".git").join("HEAD"), "").unwrap();
+
+    let options = WalkerOptions::new()
+        .set_recursive(true)
+        .set_show_hidden_files(false);
+    let mut walker = Walker::from_path(&dir, options).unwrap();
+    walker.walk_from_root().unwrap();
+    assert_eq!(walked_paths(&walker), vec!["visible.txt"]);
+
+    let mut walker = Walker::from_path(&dir, WalkerOptions::new().set_recursive(true)).unwrap();
+    walker.walk_from_root().unwrap();
+    let mut paths = walked_paths(&walker);
+    paths.sort();
+    assert_eq!(paths, vec![".git", ".git/HEAD", "visible.txt"]);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn max_depth_reached_follows_deepest_branch() {
+    let dir = test_dir("max_depth_reached");
+    std::fs::create_dir_all(dir.join("a").join("b").join("c")).unwrap();
+    std::fs::write(dir.join("a").join("b").join("c").join("leaf.txt"), "").unwrap();
+    std::fs::write(dir.join("top.txt"), "").unwrap();
+
+    let mut walker = Walker::from_path(&dir, WalkerOptions::new().set_recursive(true)).unwrap();
+    assert_eq!(walker.max_depth_reached(), 0);
+    walker.walk_from_root().unwrap();
+    assert_eq!(walker.max_depth_reached(), 4);
+
+    let mut flat = Walker::from_path(&dir, WalkerOptions::new()).unwrap();
+    flat.walk_from_root().unwrap();
+    assert_eq!(flat.max_depth_reached(), 1);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn prune_by_extension() {
+    let dir = test_dir("prune");
+    std::fs::create_dir_all(dir.join("src").join("bin")).unwrap();
+    std::fs::create_dir(dir.join("assets")).unwrap();
+    std::fs::create_dir(dir.join("empty")).unwrap();
+    std::fs::write(dir.join("src").join("lib.rs"), "").unwrap();
+    std::fs::write(dir.join("src").join("bin").join("notes.txt"), "").unwrap();
+    std::fs::write(dir.join("assets").join("logo.png"), "").unwrap();
+    std::fs::write(dir.join("Cargo.toml"), "").unwrap();
+
+    let pruned = |prune_empty: bool| {
+        let options = WalkerOptions::new()
+            .set_recursive(true)
+            .set_prune_empty_dirs(prune_empty);
+        let mut walker = Walker::from_path(&dir, options).unwrap();
+        walker.walk_from_root().unwrap();
+        walker.prune(|entry| entry.is_dir() || entry.get_extension().as_deref() == Some("rs"));
+        let mut paths = walked_paths(&walker);
+        paths.sort();
+        paths
+    };
+
+    assert_eq!(
+        pruned(false),
+        vec!["assets", "empty", "src", "src/bin", "src/lib.rs"]
+    );
+    assert_eq!(pruned(true), vec!["empty", "src", "src/lib.rs"]);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn read_errors_go_to_on_error() {
+    use std::{cell::RefCell, rc::Rc};
+
+    let seen = Rc::new(RefCell::new(Vec::new()));
+    let options = WalkerOptions::new().set_on_error({
+        let seen = Rc::clone(&seen);
+        move |path, err| seen.borrow_mut().push((path.to_path_buf(), err.kind()))
+    });
+    let missing = std::env::temp_dir().join(format!("groot-{}-missing", std::process::id()));
+
+    assert!(get_dir_entries(&missing, options.on_error.as_deref()).is_empty());
+    assert_eq!(*seen.borrow(), vec![(missing, io::ErrorKind::NotFound)]);
+}
+
+#[cfg(unix)]
+#[test]
+fn unreadable_root_is_permission_denied() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = test_dir("unreadable_root");
+    std::fs::write(dir.join("file"), "").unwrap();
+    std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o000)).unwrap();
+    //root can list it anyway
+    let readable = std::fs::read_dir(&dir).is_ok();
+
+    let mut walker = Walker::from_path(&dir, WalkerOptions::new()).unwrap();
+    let walked = walker.walk_from_root();
+    std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o755)).unwrap();
+    if !readable {
+        match walked {
+            Err(WalkerError::PermissionDenied(path)) => assert_eq!(path, dir),
+            other => panic!("expected PermissionDenied, got {other:?}"),
+        }
+        assert!(walker.root.children.is_none());
+    }
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn rewalk_rereads_only_changed_dirs() {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    let dir = test_dir("rewalk");
+    std::fs::create_dir(dir.join("same")).unwrap();
+    std::fs::create_dir(dir.join("grows")).unwrap();
+    std::fs::write(dir.join("same").join("pruned.txt"), "").unwrap();
+    //an old mtime so the new file below is sure to change it
+    let set_old_mtime = |path: &Path| {
+        std::fs::File::open(path)
+            .unwrap()
+            .set_modified(UNIX_EPOCH + Duration::from_secs(1_000_000))
+            .unwrap()
+    };
+    set_old_mtime(&dir.join("grows"));
+    set_old_mtime(&dir);
+
+    let mut walker = Walker::from_path(&dir, WalkerOptions::new().set_recursive(true)).unwrap();
+    walker.walk_from_root().unwrap();
+    //pruned entries only come back if their directory is read again
+    walker.prune(|entry| entry.is_dir());
+    std::fs::write(dir.join("grows").join("new.txt"), "").unwrap();
+    std::fs::create_dir(dir.join("added")).unwrap();
+    std::fs::write(dir.join("added").join("inner.txt"), "").unwrap();
+    walker.rewalk_changed();
+
+    let mut paths = walked_paths(&walker);
+    paths.sort();
+    assert_eq!(
+        paths,
+        vec!["added", "added/inner.txt", "grows", "grows/new.txt", "same"]
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn max_link_depth_stops_link_chains() {
+    use std::os::unix::fs::symlink;
+
+    let dir = test_dir("link_depth");
+    for name in ["a", "b", "c"] {
+        std::fs::create_dir(dir.join(name)).unwrap();
+    }
+    symlink(dir.join("b"), dir.join("a").join("to_b")).unwrap();
+    symlink(dir.join("c"), dir.join("b").join("to_c")).unwrap();
+    std::fs::write(dir.join("c").join("leaf.txt"), "").unwrap();
+
+    let walk = |max_link_depth| {
+        let options = WalkerOptions::new()
+            .set_recursive(true)
+            .set_max_link_depth(max_link_depth);
+        let mut walker = Walker::from_path(&dir.join("a"), options).unwrap();
+        walker.walk_from_root().unwrap();
+        walked_paths(&walker)
+    };
+
+    assert_eq!(walk(0), vec!["to_b"]);
+    assert_eq!(walk(1), vec!["to_b", "to_b/to_c"]);
+    assert_eq!(walk(2), vec!["to_b", "to_b/to_c", "to_b/to_c/leaf.txt"]);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn paths_are_pre_order() {
+    let mut src = FileEntry::from_path(Path::new("project/src"));
+    let mut bin = FileEntry::from_path(Path::new("project/src/bin"));
+    bin.add_child(FileEntry::from_path(Path::new("project/src/bin/tool.rs")));
+    src.add_child(bin);
+    src.add_child(FileEntry::from_path(Path::new("project/src/lib.rs")));
+    let walker = Walker::from_entries(
+        PathBuf::from("project"),
+        vec![src, FileEntry::from_path(Path::new("project/Cargo.toml"))],
+    );
+
+    let paths: Vec<(String, usize)> = walker
+        .paths()
+        .map(|(path, depth)| (path.to_string_lossy().replace('\\', "/"), depth))
+        .collect();
+    assert_eq!(
+        paths,
+        vec![
+            ("project".to_string(), 0),
+            ("project/src".to_string(), 1),
+            ("project/src/bin".to_string(), 2),
+            ("project/src/bin/tool.rs".to_string(), 3),
+            ("project/src/lib.rs".to_string(), 2),
+            ("project/Cargo.toml".to_string(), 1),
+        ]
+    );
+}
+
+#[test]
+fn binary_round_trip() {
+    let dir = test_dir("binary");
+    std::fs::create_dir_all(dir.join("src").join("bin")).unwrap();
+    std::fs::write(dir.join("src").join("lib.rs"), "").unwrap();
+    std::fs::write(dir.join("src").join("bin").join("tool.rs"), "").unwrap();
+    std::fs::write(dir.join("Cargo.toml"), "").unwrap();
+
+    let mut walker = Walker::from_path(&dir, WalkerOptions::new().set_recursive(true)).unwrap();
+    walker.walk_from_root().unwrap();
+    //an entry that isn't below its parent keeps its full path
+    walker
+        .root
+        .add_child(FileEntry::from_path(Path::new("elsewhere/notes.txt")));
+    let mut before_epoch = FileEntry::from_path(&dir.join("old"));
+    before_epoch.modified = UNIX_EPOCH.checked_sub(Duration::new(5, 250));
+    walker.root.add_child(before_epoch);
+
+    let mut saved = Vec::new();
+    walker.write_binary(&mut saved).unwrap();
+    let reloaded = Walker::read_binary(&mut saved.as_slice()).unwrap();
+    assert_eq!(reloaded.root, walker.root);
+
+    assert!(Walker::read_binary(&mut &saved[..saved.len() - 1]).is_err());
+    assert!(Walker::read_binary(&mut &b"JSON{}"[..]).is_err());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn timeout_keeps_a_partial_tree() {
+    let dir = test_dir("timeout");
+    std::fs::create_dir_all(dir.join("a").join("b")).unwrap();
+    std::fs::write(dir.join("a").join("b").join("deep.txt"), "").unwrap();
+
+    let options = WalkerOptions::new()
+        .set_recursive(true)
+        .set_timeout(Duration::ZERO);
+    let mut walker = Walker::from_path(&dir, options).unwrap();
+    assert!(matches!(walker.walk_from_root(), Err(WalkerError::Timeout)));
+    //not even the root was read in time
+    assert_eq!(walker.max_depth_reached(), 0);
+
+    let options = WalkerOptions::new()
+        .set_recursive(true)
+        .set_timeout(Duration::from_secs(60));
+    let mut walker = Walker::from_path(&dir, options).unwrap();
+    walker.walk_from_root().unwrap();
+    assert_eq!(walker.max_depth_reached(), 3);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn hidden_entries_are_counted() {
+    let dir = test_dir("hidden_count");
+    std::fs::create_dir(dir.join("sub")).unwrap();
+    std::fs::write(dir.join(".env"), "").unwrap();
+    std::fs::write(dir.join("sub").join(".cache"), "").unwrap();
+    std::fs::write(dir.join("sub").join(".lock"), "").unwrap();
+
+    let hiding = || WalkerOptions::new().set_show_hidden_files(false);
+    let mut walker = Walker::from_path(&dir, hiding().set_recursive(true)).unwrap();
+    walker.walk_from_root().unwrap();
+    assert_eq!(walker.root.hidden_count(), 1);
+    assert_eq!(walker.hidden_count(), 3);
+    let mut saved = Vec::new();
+    walker.write_binary(&mut saved).unwrap();
+    assert_eq!(
+        Walker::read_binary(&mut saved.as_slice())
+            .unwrap()
+            .hidden_count(),
+        3
+    );
+
+    let mut flat = Walker::from_path(&dir, hiding()).unwrap();
+    flat.walk_from_root().unwrap();
+    assert_eq!(flat.hidden_count(), 1);
+
+    let mut all = Walker::from_path(&dir, WalkerOptions::new().set_recursive(true)).unwrap();
+    all.walk_from_root().unwrap();
+    assert_eq!(all.hidden_count(), 0);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn display_transform_only_changes_output() {
+    let dir = test_dir("display_transform");
+    std::fs::write(dir.join("secret.txt"), "").unwrap();
+
+    let options =
+        WalkerOptions::new().set_display_transform(|name| "*".repeat(name.chars().count()));
+    let mut walker = Walker::from_path(&dir, options).unwrap();
+    walker.walk_from_root().unwrap();
+    let paths: Vec<PathBuf> = walker.paths().map(|(path, _)| path).collect();
+    assert!(paths.contains(&dir.join("secret.txt")));
+
+    let mut out = Vec::new();
+    walker.write_listing(&mut out).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    assert!(!out.contains("secret"));
+    assert!(out.contains("\t********** \t"));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(feature = "petgraph")]
+#[test]
+fn graph_has_an_edge_per_entry() {
+    let dir = test_dir("graph");
+    std::fs::create_dir(dir.join("src")).unwrap();
+    std::fs::write(dir.join("src").join("lib.rs"), "mod a;").unwrap();
+    std::fs::write(dir.join("Cargo.toml"), "").unwrap();
+
+    let mut walker = Walker::from_path(&dir, WalkerOptions::new().set_recursive(true)).unwrap();
+    walker.walk_from_root().unwrap();
+    let graph = walker.to_graph();
+    assert_eq!((graph.node_count(), graph.edge_count()), (4, 3));
+
+    let root = petgraph::graph::NodeIndex::new(0);
+    assert_eq!(graph[root].kind, NodeKind::Directory);
+    let lib = graph
+        .node_indices()
+        .find(|&node| graph[node].path.ends_with("lib.rs"))
+        .unwrap();
+    assert_eq!(
+        (graph[lib].kind, graph[lib].size),
+        (NodeKind::File, Some(6))
+    );
+    //the only way from the root to lib.rs goes through src
+    let src = graph
+        .neighbors_directed(lib, petgraph::Incoming)
+        .next()
+        .unwrap();
+    assert!(graph[src].path.ends_with("src"));
+    assert!(graph.contains_edge(root, src));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn largest_files_are_the_biggest_first() {
+    let dir = test_dir("largest");
+    std::fs::create_dir(dir.join("src")).unwrap();
+    std::fs::write(dir.join("small"), "1").unwrap();
+    std::fs::write(dir.join("src").join("big"), "12345").unwrap();
+    std::fs::write(dir.join("src").join("tie"), "123").unwrap();
+    std::fs::write(dir.join("medium"), "123").unwrap();
+
+    //the entries are given in a known order, so the tie goes to the file found first
+    let mut src = FileEntry::from_path(&dir.join("src"));
+    src.set_children(Some(vec![
+        FileEntry::from_path(&dir.join("src").join("big")),
+        FileEntry::from_path(&dir.join("src").join("tie")),
+    ]));
+    let walker = Walker::from_entries(
+        dir.clone(),
+        vec![
+            FileEntry::from_path(&dir.join("small")),
+            src,
+            FileEntry::from_path(&dir.join("medium")),
+        ],
+    );
+    let names = |files: Vec<&FileEntry>| -> Vec<String> {
+        files
+            .iter()
+            .map(|file| {
+                file.get_path()
+                    .file_name()
+                    .unwrap()
+                    .to_string_lossy()
+                    .into()
+            })
+            .collect()
+    };
+    assert_eq!(names(walker.largest_files(2)), vec!["big", "tie"]);
+    assert_eq!(
+        names(walker.largest_files(10)),
+        vec!["big", "tie", "medium", "small"]
+    );
+    assert!(walker.largest_files(0).is_empty());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn map_entries_keeps_the_some_results() {
+    let mut src = FileEntry::from_path(Path::new("project/src"));
+    src.add_child(FileEntry::from_path(Path::new("project/src/lib.rs")));
+    let walker = Walker::from_entries(
+        PathBuf::from("project"),
+        vec![src, FileEntry::from_path(Path::new("project/notes.txt"))],
+    );
+
+    let names = walker.map_entries(|entry| {
+        let extension = entry.get_extension()?;
+        Some(format!("{entry} ({extension})"))
+    });
+    assert_eq!(names, vec!["lib.rs (rs)", "notes.txt (txt)"]);
+}
+
+#[test]
+fn walk_channel_streams_every_entry() {
+    let dir = test_dir("walk_channel");
+    std::fs::create_dir(dir.join("src")).unwrap();
+    std::fs::write(dir.join("src").join("main.rs"), "").unwrap();
+
+    let walker = Walker::from_path(&dir, WalkerOptions::new().set_recursive(true)).unwrap();
+    let entries: Vec<(PathBuf, usize, String)> = walker
+        .walk_channel()
+        .into_iter()
+        .map(|(path, depth, kind)| (path, depth, format!("{kind:?}")))
+        .collect();
+    assert_eq!(
+        entries,
+        vec![
+            (dir.clone(), 0, "Directory".to_string()),
+            (dir.join("src"), 1, "Directory".to_string()),
+            (dir.join("src").join("main.rs"), 2, "File".to_string()),
+        ]
+    );
+
+    let options = WalkerOptions::new()
+        .set_recursive(true)
+        .set_timeout(Duration::ZERO);
+    let walker = Walker::from_path(&dir, options).unwrap();
+    let entries: Vec<_> = walker.walk_channel().into_iter().collect();
+    assert_eq!(entries.len(), 2);
+    match &entries[1] {
+        (path, 0, EntryKind::Error(err)) => {
+            assert_eq!((path, err.kind()), (&dir, io::ErrorKind::TimedOut))
+        }
+        other => panic!("expected the root to time out, got {other:?}"),
+    }
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn roots_without_a_file_name_display() {
+    assert_eq!(FileEntry::from_path(Path::new("/")).to_string(), "/");
+    assert_eq!(FileEntry::from_path(Path::new(".")).to_string(), ".");
+    assert_eq!(FileEntry::from_path(Path::new("")).to_string(), ".");
+    assert_eq!(FileEntry::from_path(Path::new(r"C:\")).to_string(), r"C:\");
+    assert_eq!(
+        FileEntry::from_path(Path::new("src/lib.rs")).to_string(),
+        "lib.rs"
+    );
+}
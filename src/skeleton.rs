@@ -0,0 +1,50 @@
+use std::io::{self, Write};
+
+use crate::{Directory, TreeEntry};
+
+/// Prints `mkdir -p`/`touch` commands that recreate the walked directories and files,
+/// relative to the directory the script is run from. Contents and symlinks aren't reproduced.
+pub fn print_mkdir_script(out: &mut dyn Write, tree: &Directory) -> io::Result<()> {
+    return visit(out, tree, "");
+
+    fn visit(out: &mut dyn Write, dir: &Directory, pre: &str) -> io::Result<()> {
+        for entry in dir.subdirectories.iter() {
+            let path = format!("{}{}", pre, entry.name());
+            match entry {
+                TreeEntry::FileNode(_) => writeln!(out, "touch {}", shell_quote(&path))?,
+                TreeEntry::DirNode(dir_entry) => {
+                    writeln!(out, "mkdir -p {}", shell_quote(&path))?;
+                    visit(out, dir_entry, &format!("{}/", path))?;
+                }
+                TreeEntry::SymlinkNode(_) => (),
+            }
+        }
+        Ok(())
+    }
+}
+
+//single quotes keep everything literal; an embedded ' has to close the quote, be escaped and reopen it
+fn shell_quote(path: &str) -> String {
+    //a leading '-' would otherwise be read as an option
+    let path = if path.starts_with('-') {
+        format!("./{path}")
+    } else {
+        path.to_string()
+    };
+
+    let is_plain = |c: char| c.is_ascii_alphanumeric() || "_-./+,:@%".contains(c);
+    if !path.is_empty() && path.chars().all(is_plain) {
+        path
+    } else {
+        format!("'{}'", path.replace('\'', r"'\''"))
+    }
+}
+
+#[test]
+fn quote_special_names() {
+    assert_eq!(shell_quote("src/main.rs"), "src/main.rs");
+    assert_eq!(shell_quote("my docs/notes.txt"), "'my docs/notes.txt'");
+    assert_eq!(shell_quote("it's"), r"'it'\''s'");
+    assert_eq!(shell_quote("$HOME"), "'$HOME'");
+    assert_eq!(shell_quote("-rf"), "./-rf");
+}
@@ -0,0 +1,592 @@
+use std::{cmp::Ordering, fs, str::FromStr, time::SystemTime};
+
+use anyhow::{bail, Error};
+
+use crate::{date::TimeType, normalize, Directory, TreeEntry};
+
+/// `--sort`: the order entries are listed in within each directory.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum SortOrder {
+    //case-insensitive, so output doesn't depend on the filesystem
+    #[default]
+    Name,
+    //largest first; a directory weighs what its files do
+    Size,
+    //newest first, by the --time-type timestamp
+    Modified,
+    //by creation time; entries whose filesystem doesn't keep it go last either way
+    CreatedAsc,
+    CreatedDesc,
+    //directories by how many entries they hold directly; files and symlinks go last either way
+    ChildCountAsc,
+    ChildCountDesc,
+    //whatever order the directory was read in
+    None,
+}
+
+impl FromStr for SortOrder {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "name" => Ok(SortOrder::Name),
+            "size" => Ok(SortOrder::Size),
+            "mtime" => Ok(SortOrder::Modified),
+            "created" => Ok(SortOrder::CreatedDesc),
+            "created-asc" => Ok(SortOrder::CreatedAsc),
+            "count" => Ok(SortOrder::ChildCountDesc),
+            "count-asc" => Ok(SortOrder::ChildCountAsc),
+            "none" => Ok(SortOrder::None),
+            _ => bail!(
+                "invalid --sort: {s}, expected name, size, mtime, created, created-asc, count, count-asc or none"
+            ),
+        }
+    }
+}
+
+//an entry with its size for --sort size, added up once per tree rather than on every comparison
+type SizedEntry = (u64, TreeEntry);
+
+impl SortOrder {
+    //`sorting` decides which timestamp and how names compare
+    fn compare(self, a: &SizedEntry, b: &SizedEntry, sorting: &Sorting) -> Ordering {
+        let ((a_size, a), (b_size, b)) = (a, b);
+        let by_key = match self {
+            //stable sorting keeps the listing order
+            SortOrder::None => return Ordering::Equal,
+            SortOrder::Name => Ordering::Equal,
+            SortOrder::Size => b_size.cmp(a_size),
+            SortOrder::Modified => {
+                let time = |entry| time(entry, sorting.time_type);
+                time(b).cmp(&time(a))
+            }
+            SortOrder::CreatedAsc => match (created(a), created(b)) {
+                (Some(a), Some(b)) => a.cmp(&b),
+                (a, b) => b.is_some().cmp(&a.is_some()),
+            },
+            SortOrder::CreatedDesc => created(b).cmp(&created(a)),
+            SortOrder::ChildCountAsc => match (child_count(a), child_count(b)) {
+                (Some(a), Some(b)) => a.cmp(&b),
+                (a, b) => b.is_some().cmp(&a.is_some()),
+            },
+            SortOrder::ChildCountDesc => child_count(b).cmp(&child_count(a)),
+        };
+        //names break ties so every order is reproducible
+        let a_name = normalize::comparable(a.name(), sorting.normalize_names);
+        let b_name = normalize::comparable(b.name(), sorting.normalize_names);
+        by_key
+            .then_with(|| a_name.to_lowercase().cmp(&b_name.to_lowercase()))
+            .then_with(|| a_name.cmp(&b_name))
+    }
+}
+
+//the walk has already read every directory, so this is just the length of its listing
+fn child_count(entry: &TreeEntry) -> Option<usize> {
+    match entry {
+        TreeEntry::DirNode(dir) => Some(dir.subdirectories.len()),
+        _ => None,
+    }
+}
+
+fn metadata(entry: &TreeEntry) -> Option<&fs::Metadata> {
+    match entry {
+        TreeEntry::FileNode(file) => file.metadata.as_ref(),
+        TreeEntry::SymlinkNode(link) => link.link_metadata.as_ref(),
+        TreeEntry::DirNode(_) => None,
+    }
+}
+
+fn time(entry: &TreeEntry, time_type: TimeType) -> Option<SystemTime> {
+    time_type.of(metadata(entry)?)
+}
+
+fn created(entry: &TreeEntry) -> Option<SystemTime> {
+    metadata(entry)?.created().ok()
+}
+
+/// The kinds of entries `--group-order` lists one after another.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EntryGroup {
+    Dirs,
+    Files,
+    Links,
+}
+
+impl EntryGroup {
+    fn of(entry: &TreeEntry) -> Self {
+        match entry {
+            TreeEntry::DirNode(_) => EntryGroup::Dirs,
+            TreeEntry::FileNode(_) => EntryGroup::Files,
+            TreeEntry::SymlinkNode(_) => EntryGroup::Links,
+        }
+    }
+}
+
+/// `--group-order`: each of the three kinds of entries exactly once, e.g. `dirs,files,links`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GroupOrder(pub [EntryGroup; 3]);
+
+impl FromStr for GroupOrder {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut groups = Vec::new();
+        for group in s.split(',') {
+            let group = match group.trim() {
+                "dirs" => EntryGroup::Dirs,
+                "files" => EntryGroup::Files,
+                "links" => EntryGroup::Links,
+                other => bail!(
+                    "invalid --group-order: unknown group {other}, expected dirs, files or links"
+                ),
+            };
+            if groups.contains(&group) {
+                bail!("invalid --group-order: {s} names a group twice");
+            }
+            groups.push(group);
+        }
+        match groups[..] {
+            [first, second, third] => Ok(GroupOrder([first, second, third])),
+            _ => bail!("invalid --group-order: {s}, expected all of dirs, files and links"),
+        }
+    }
+}
+
+impl GroupOrder {
+    fn rank(&self, entry: &TreeEntry) -> usize {
+        let group = EntryGroup::of(entry);
+        self.0.iter().position(|g| *g == group).unwrap_or(0)
+    }
+}
+
+/// Everything that decides the order of a directory's entries.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Sorting {
+    pub order: SortOrder,
+    //--dirs-first lists subdirectories before files and symlinks
+    pub dirs_first: bool,
+    //with dirs_first, each group can have its own order instead of `order`
+    pub dirs: Option<SortOrder>,
+    pub files: Option<SortOrder>,
+    //--group-order, which takes the place of dirs_first; symlinks use the files' order
+    pub groups: Option<GroupOrder>,
+    //--compare-names-unicode-normalized
+    pub normalize_names: bool,
+    //--time-type, for the mtime order
+    pub time_type: TimeType,
+}
+
+impl Sorting {
+    fn compare(&self, a: &SizedEntry, b: &SizedEntry) -> Ordering {
+        if let Some(groups) = &self.groups {
+            let group_order = match EntryGroup::of(&a.1) {
+                EntryGroup::Dirs => self.dirs.unwrap_or(self.order),
+                EntryGroup::Files | EntryGroup::Links => self.files.unwrap_or(self.order),
+            };
+            return groups
+                .rank(&a.1)
+                .cmp(&groups.rank(&b.1))
+                .then_with(|| group_order.compare(a, b, self));
+        }
+        if !self.dirs_first {
+            return self.order.compare(a, b, self);
+        }
+        let is_dir = |entry: &TreeEntry| matches!(entry, TreeEntry::DirNode(_));
+        match (is_dir(&a.1), is_dir(&b.1)) {
+            (true, false) => Ordering::Less,
+            (false, true) => Ordering::Greater,
+            (true, true) => self.dirs.unwrap_or(self.order).compare(a, b, self),
+            (false, false) => self.files.unwrap_or(self.order).compare(a, b, self),
+        }
+    }
+}
+
+/// Orders every directory's entries, all the way down.
+pub fn sort_tree(dir: &mut Directory, sorting: &Sorting) {
+    sort_dir(dir, sorting);
+}
+
+//post-order, so each directory's size is added up from its entries' once. Returns the size of
+//the files below `dir`
+fn sort_dir(dir: &mut Directory, sorting: &Sorting) -> u64 {
+    let mut entries: Vec<SizedEntry> = std::mem::take(&mut dir.subdirectories)
+        .into_iter()
+        .map(|mut entry| {
+            let size = match &mut entry {
+                TreeEntry::FileNode(file) => file.size.unwrap_or(0),
+                TreeEntry::DirNode(child) => sort_dir(child, sorting),
+                TreeEntry::SymlinkNode(_) => 0,
+            };
+            (size, entry)
+        })
+        .collect();
+    entries.sort_by(|a, b| sorting.compare(a, b));
+    let bytes = entries.iter().map(|(size, _)| size).sum();
+    dir.subdirectories = entries.into_iter().map(|(_, entry)| entry).collect();
+    bytes
+}
+
+#[test]
+fn sorts_by_name_then_key() {
+    use crate::{test_dir_node, test_file};
+
+    let tree = || Directory {
+        name: "root".to_string(),
+        subdirectories: vec![
+            test_file("b.txt", Some(10)),
+            test_file("README", Some(1)),
+            test_file("a.txt", Some(10)),
+            test_dir_node(
+                "Src",
+                vec![test_file("z", Some(0)), test_file("m", Some(0))],
+            ),
+        ],
+        error: None,
+        skipped: None,
+    };
+    let names = |order: SortOrder| {
+        let mut tree = tree();
+        sort_tree(
+            &mut tree,
+            &Sorting {
+                order,
+                ..Default::default()
+            },
+        );
+        let mut names: Vec<String> = tree
+            .subdirectories
+            .iter()
+            .map(|entry| entry.name().to_string())
+            .collect();
+        if let Some(TreeEntry::DirNode(src)) = tree
+            .subdirectories
+            .iter()
+            .find(|entry| entry.name() == "Src")
+        {
+            names.extend(
+                src.subdirectories
+                    .iter()
+                    .map(|e| format!("Src/{}", e.name())),
+            );
+        }
+        names
+    };
+
+    assert_eq!(
+        names(SortOrder::default()),
+        vec!["a.txt", "b.txt", "README", "Src", "Src/m", "Src/z"]
+    );
+    assert_eq!(
+        names(SortOrder::Size),
+        vec!["a.txt", "b.txt", "README", "Src", "Src/m", "Src/z"]
+    );
+    assert_eq!(
+        names(SortOrder::None),
+        vec!["b.txt", "README", "a.txt", "Src", "Src/z", "Src/m"]
+    );
+}
+
+#[test]
+fn dirs_first_with_separate_orders() {
+    use crate::{test_dir_node, test_file};
+
+    let names = |sorting: Sorting| {
+        let mut tree = Directory {
+            name: "root".to_string(),
+            subdirectories: vec![
+                test_file("small.txt", Some(1)),
+                test_dir_node("a-light", vec![test_file("x", Some(5))]),
+                test_file("big.txt", Some(100)),
+                test_dir_node(
+                    "b-heavy",
+                    vec![test_file("y", Some(50)), test_file("z", Some(50))],
+                ),
+            ],
+            error: None,
+            skipped: None,
+        };
+        sort_tree(&mut tree, &sorting);
+        tree.subdirectories
+            .iter()
+            .map(|entry| entry.name().to_string())
+            .collect::<Vec<_>>()
+    };
+
+    //directories by size, files by name
+    assert_eq!(
+        names(Sorting {
+            dirs_first: true,
+            dirs: Some(SortOrder::Size),
+            ..Default::default()
+        }),
+        vec!["b-heavy", "a-light", "big.txt", "small.txt"]
+    );
+    //the other way around, falling back to --sort for directories
+    assert_eq!(
+        names(Sorting {
+            order: SortOrder::Name,
+            dirs_first: true,
+            files: Some(SortOrder::Size),
+            ..Default::default()
+        }),
+        vec!["a-light", "b-heavy", "big.txt", "small.txt"]
+    );
+    //split orders only apply to the groups --dirs-first makes
+    assert_eq!(
+        names(Sorting {
+            order: SortOrder::Size,
+            dirs: Some(SortOrder::Name),
+            ..Default::default()
+        }),
+        vec!["b-heavy", "big.txt", "a-light", "small.txt"]
+    );
+    //busiest directories first, then the files by name
+    assert_eq!(
+        names(Sorting {
+            order: "count".parse().unwrap(),
+            ..Default::default()
+        }),
+        vec!["b-heavy", "a-light", "big.txt", "small.txt"]
+    );
+    assert_eq!(
+        names(Sorting {
+            order: SortOrder::ChildCountAsc,
+            ..Default::default()
+        }),
+        vec!["a-light", "b-heavy", "big.txt", "small.txt"]
+    );
+}
+
+#[test]
+fn created_orders_put_unknown_times_last() {
+    use crate::{test_file, File, FileKind};
+
+    let dir = crate::test_dir("sort_created");
+    let file = |name: &str| {
+        let path = dir.join(name);
+        std::fs::write(&path, "").unwrap();
+        TreeEntry::FileNode(File {
+            name: name.to_string(),
+            metadata: std::fs::metadata(&path).ok(),
+            kind: FileKind::Regular,
+            path,
+            size: Some(0),
+        })
+    };
+    let first = file("first");
+    std::thread::sleep(std::time::Duration::from_millis(20));
+    let second = file("second");
+    let unknown = test_file("a-unknown", None);
+    let supported = created(&first).is_some();
+    let mut tree = Directory {
+        name: "root".to_string(),
+        subdirectories: vec![unknown, second, first],
+        error: None,
+        skipped: None,
+    };
+    let names = |tree: &mut Directory, order: &str| {
+        let sorting = Sorting {
+            order: order.parse().unwrap(),
+            ..Default::default()
+        };
+        sort_tree(tree, &sorting);
+        tree.subdirectories
+            .iter()
+            .map(|entry| entry.name().to_string())
+            .collect::<Vec<_>>()
+    };
+
+    //not every filesystem records when a file was created
+    if supported {
+        assert_eq!(
+            names(&mut tree, "created"),
+            vec!["second", "first", "a-unknown"]
+        );
+        assert_eq!(
+            names(&mut tree, "created-asc"),
+            vec!["first", "second", "a-unknown"]
+        );
+    } else {
+        assert_eq!(
+            names(&mut tree, "created-asc"),
+            vec!["a-unknown", "first", "second"]
+        );
+    }
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn group_order_puts_links_where_asked() {
+    use crate::{test_dir_node, test_file, Symlink};
+
+    let link = |name: &str| {
+        TreeEntry::SymlinkNode(Symlink {
+            name: name.to_string(),
+            path: name.into(),
+            target: "elsewhere".to_string(),
+            link_metadata: None,
+            target_metadata: None,
+        })
+    };
+    let names = |groups: &str| {
+        let mut tree = Directory {
+            name: "root".to_string(),
+            subdirectories: vec![
+                link("a-link"),
+                test_file("b.txt", Some(0)),
+                test_dir_node("c", Vec::new()),
+                test_file("a.txt", Some(0)),
+                test_dir_node("b", Vec::new()),
+            ],
+            error: None,
+            skipped: None,
+        };
+        sort_tree(
+            &mut tree,
+            &Sorting {
+                groups: Some(groups.parse().unwrap()),
+                ..Default::default()
+            },
+        );
+        tree.subdirectories
+            .iter()
+            .map(|entry| entry.name().to_string())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        names("dirs,files,links"),
+        vec!["b", "c", "a.txt", "b.txt", "a-link"]
+    );
+    assert_eq!(
+        names("links, dirs, files"),
+        vec!["a-link", "b", "c", "a.txt", "b.txt"]
+    );
+    assert!("dirs,files".parse::<GroupOrder>().is_err());
+    assert!("dirs,files,files".parse::<GroupOrder>().is_err());
+    assert!("dirs,files,devices".parse::<GroupOrder>().is_err());
+}
+
+#[cfg(feature = "unicode-normalization")]
+#[test]
+fn normalized_names_sort_composed() {
+    use crate::test_file;
+
+    let names = |normalize_names: bool| {
+        let mut tree = Directory {
+            name: "root".to_string(),
+            subdirectories: vec![
+                test_file("f", None),
+                test_file("e\u{301}", None),
+                test_file("\u{e9}", None),
+            ],
+            error: None,
+            skipped: None,
+        };
+        sort_tree(
+            &mut tree,
+            &Sorting {
+                normalize_names,
+                ..Default::default()
+            },
+        );
+        tree.subdirectories
+            .iter()
+            .map(|entry| entry.name().to_string())
+            .collect::<Vec<_>>()
+    };
+
+    //decomposed, the accent comes after a plain 'e'; composed, 'é' is past every ASCII letter
+    assert_eq!(names(false), vec!["e\u{301}", "f", "\u{e9}"]);
+    //and both forms are the same name, kept in listing order
+    assert_eq!(names(true), vec!["f", "e\u{301}", "\u{e9}"]);
+}
+
+#[test]
+fn size_counts_nested_files() {
+    use crate::{test_dir_node, test_file};
+
+    let mut tree = Directory {
+        name: "root".to_string(),
+        subdirectories: vec![
+            test_dir_node(
+                "flat",
+                vec![test_file("a", Some(30)), test_file("b", Some(30))],
+            ),
+            test_file("file", Some(50)),
+            test_dir_node(
+                "deep",
+                vec![test_dir_node("inner", vec![test_file("c", Some(70))])],
+            ),
+        ],
+        error: None,
+        skipped: None,
+    };
+    sort_tree(
+        &mut tree,
+        &Sorting {
+            order: SortOrder::Size,
+            ..Default::default()
+        },
+    );
+    let names: Vec<&str> = tree.subdirectories.iter().map(TreeEntry::name).collect();
+    assert_eq!(names, vec!["deep", "flat", "file"]);
+}
+
+#[test]
+fn mtime_order_follows_time_type() {
+    use std::{
+        fs::FileTimes,
+        time::{Duration, UNIX_EPOCH},
+    };
+
+    use crate::{File, FileKind};
+
+    let dir = crate::test_dir("sort_time_type");
+    //modified and accessed in opposite orders
+    for (name, modified, accessed) in [("edited", 2_000, 1_000), ("read", 1_000, 2_000)] {
+        let times = FileTimes::new()
+            .set_modified(UNIX_EPOCH + Duration::from_secs(modified))
+            .set_accessed(UNIX_EPOCH + Duration::from_secs(accessed));
+        std::fs::File::create(dir.join(name))
+            .unwrap()
+            .set_times(times)
+            .unwrap();
+    }
+    let file = |name: &str| {
+        let path = dir.join(name);
+        TreeEntry::FileNode(File {
+            name: name.to_string(),
+            metadata: std::fs::metadata(&path).ok(),
+            kind: FileKind::Regular,
+            path,
+            size: Some(0),
+        })
+    };
+    let names = |time_type: &str| {
+        let mut tree = Directory {
+            name: "root".to_string(),
+            subdirectories: vec![file("edited"), file("read")],
+            error: None,
+            skipped: None,
+        };
+        sort_tree(
+            &mut tree,
+            &Sorting {
+                order: SortOrder::Modified,
+                time_type: time_type.parse().unwrap(),
+                ..Default::default()
+            },
+        );
+        tree.subdirectories
+            .iter()
+            .map(|entry| entry.name().to_string())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(names("mtime"), vec!["edited", "read"]);
+    assert_eq!(names("atime"), vec!["read", "edited"]);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,208 @@
+use std::{borrow::Cow, str::FromStr};
+
+use anyhow::{bail, Error};
+
+use crate::{
+    color::{self, Color},
+    L_PIPE, PIPE, SPACES, TEE_PIPE,
+};
+
+/// How `--encoding` writes the tree: plain UTF-8, UTF-8 behind a byte order mark
+/// for editors that guess encodings, or ASCII-only connectors.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    Utf8Bom,
+    Ascii,
+}
+
+impl FromStr for Encoding {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "utf8" => Ok(Encoding::Utf8),
+            "utf8-bom" => Ok(Encoding::Utf8Bom),
+            "ascii" => Ok(Encoding::Ascii),
+            _ => bail!("unknown encoding: {s}, expected utf8, utf8-bom or ascii"),
+        }
+    }
+}
+
+impl Encoding {
+    pub const BOM: &'static str = "\u{FEFF}";
+
+    pub fn style(self) -> RenderStyle {
+        match self {
+            Encoding::Utf8 | Encoding::Utf8Bom => RenderStyle::UNICODE,
+            Encoding::Ascii => RenderStyle::ASCII,
+        }
+    }
+}
+
+/// The connectors drawn in front of each entry.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderStyle {
+    //continues a parent's branch past this level
+    pub pipe: Cow<'static, str>,
+    //an entry with more siblings below it
+    pub tee: Cow<'static, str>,
+    //the last entry of a directory
+    pub last: Cow<'static, str>,
+    //the first entry of a directory when drawn bottom-up by --reverse-depth
+    pub first: Cow<'static, str>,
+    //indents below a parent that was the last entry
+    pub space: Cow<'static, str>,
+    //stands in for entries that aren't shown
+    pub ellipsis: Cow<'static, str>,
+}
+
+impl Default for RenderStyle {
+    fn default() -> Self {
+        RenderStyle::UNICODE
+    }
+}
+
+impl RenderStyle {
+    pub const UNICODE: RenderStyle = RenderStyle {
+        pipe: Cow::Borrowed(PIPE),
+        tee: Cow::Borrowed(TEE_PIPE),
+        last: Cow::Borrowed(L_PIPE),
+        first: Cow::Borrowed("\u{250c}\u{2500} "),
+        space: Cow::Borrowed(SPACES),
+        ellipsis: Cow::Borrowed("\u{2026}"),
+    };
+
+    //same widths as UNICODE so switching keeps the layout
+    pub const ASCII: RenderStyle = RenderStyle {
+        pipe: Cow::Borrowed("|  "),
+        tee: Cow::Borrowed("|-- "),
+        last: Cow::Borrowed("`- "),
+        first: Cow::Borrowed(",- "),
+        space: Cow::Borrowed("   "),
+        ellipsis: Cow::Borrowed("..."),
+    };
+
+    /// Replaces the four connectors with `--tree-chars` ones given as
+    /// `<pipe>,<tee>,<last>,<space>`, keeping this style's ellipsis. The bottom-up corner
+    /// becomes the given last one, as there is nothing to mirror it from.
+    pub fn with_tree_chars(self, chars: &str) -> Result<RenderStyle, Error> {
+        let connectors: Vec<&str> = chars.split(',').collect();
+        let [pipe, tee, last, space] = connectors[..] else {
+            bail!(
+                "invalid --tree-chars: {chars}, expected exactly four comma-separated connectors"
+            );
+        };
+        Ok(RenderStyle {
+            pipe: Cow::Owned(pipe.to_string()),
+            tee: Cow::Owned(tee.to_string()),
+            last: Cow::Owned(last.to_string()),
+            first: Cow::Owned(last.to_string()),
+            space: Cow::Owned(space.to_string()),
+            ..self
+        })
+    }
+
+    /// `--no-guides`: every connector becomes as many spaces as it has characters, so entries
+    /// keep their indentation without the lines drawn between them.
+    pub fn without_guides(self) -> RenderStyle {
+        let blank = |connector: Cow<str>| Cow::Owned(" ".repeat(connector.chars().count()));
+        RenderStyle {
+            pipe: blank(self.pipe),
+            tee: blank(self.tee),
+            last: blank(self.last),
+            first: blank(self.first),
+            space: blank(self.space),
+            ellipsis: self.ellipsis,
+        }
+    }
+
+    /// `--guide-color`: the connectors are painted on their own, so entry names keep the
+    /// colors they get from elsewhere. Blank indentation is left as it is.
+    pub fn with_guide_color(self, guide_color: Color) -> RenderStyle {
+        let paint = |connector: Cow<str>| Cow::Owned(color::paint(&connector, guide_color));
+        RenderStyle {
+            pipe: paint(self.pipe),
+            tee: paint(self.tee),
+            last: paint(self.last),
+            first: paint(self.first),
+            ..self
+        }
+    }
+}
+
+#[test]
+fn ascii_style_is_ascii() {
+    let style = "ascii".parse::<Encoding>().unwrap().style();
+    for connector in [
+        &style.pipe,
+        &style.tee,
+        &style.last,
+        &style.first,
+        &style.space,
+        &style.ellipsis,
+    ] {
+        assert!(connector.is_ascii());
+    }
+    for (ascii, unicode) in [
+        (&style.pipe, PIPE),
+        (&style.tee, TEE_PIPE),
+        (&style.last, L_PIPE),
+        (&style.first, &RenderStyle::UNICODE.first),
+        (&style.space, SPACES),
+    ] {
+        assert_eq!(ascii.len(), unicode.chars().count());
+    }
+    assert!("utf16".parse::<Encoding>().is_err());
+}
+
+#[test]
+fn tree_chars_replace_connectors() {
+    let style = Encoding::Ascii
+        .style()
+        .with_tree_chars("| ,+- ,\\- ,  ")
+        .unwrap();
+    assert_eq!(
+        [
+            &style.pipe,
+            &style.tee,
+            &style.last,
+            &style.space,
+            &style.ellipsis
+        ],
+        ["| ", "+- ", "\\- ", "  ", "..."]
+    );
+    assert!(RenderStyle::UNICODE.with_tree_chars("|,+,\\").is_err());
+    assert!(RenderStyle::UNICODE.with_tree_chars("|,+,\\, ,x").is_err());
+}
+
+#[test]
+fn no_guides_keeps_widths() {
+    let style = RenderStyle::UNICODE.without_guides();
+    assert_eq!(
+        [&style.pipe, &style.tee, &style.last, &style.space],
+        ["   ", "    ", "   ", "   "]
+    );
+    assert_eq!(style.ellipsis, RenderStyle::UNICODE.ellipsis);
+    let custom = RenderStyle::ASCII
+        .with_tree_chars("|,+--,\\--, ")
+        .unwrap()
+        .without_guides();
+    assert_eq!(
+        [&custom.pipe, &custom.tee, &custom.last, &custom.space],
+        [" ", "   ", "   ", " "]
+    );
+}
+
+#[test]
+fn guide_color_paints_connectors_only() {
+    let style = RenderStyle::ASCII.with_guide_color("gray".parse().unwrap());
+    assert_eq!(style.tee, "\x1b[90m|-- \x1b[0m");
+    assert_eq!(style.pipe, "\x1b[90m|  \x1b[0m");
+    assert_eq!(
+        [&style.space, &style.ellipsis],
+        [&RenderStyle::ASCII.space, &RenderStyle::ASCII.ellipsis]
+    );
+    assert!("purple".parse::<Color>().is_err());
+}
@@ -0,0 +1,271 @@
+use std::time::SystemTime;
+
+use crate::{
+    date::{DateFormat, TimeType},
+    Directory, TreeEntry,
+};
+
+/// Totals over a walked tree. The root counts as one of the directories.
+#[derive(Debug, Default, PartialEq)]
+pub struct Summary {
+    pub files: usize,
+    pub directories: usize,
+    pub symlinks: usize,
+    //sum of file sizes; symlinks and directories add nothing
+    pub bytes: u64,
+    //entries at each depth, the root being the one entry at depth 0
+    pub by_depth: Vec<usize>,
+    //the span of file modification times, or the --time-type ones, from files whose metadata
+    //was read
+    pub oldest: Option<SystemTime>,
+    pub newest: Option<SystemTime>,
+}
+
+impl Summary {
+    pub fn of(tree: &Directory) -> Self {
+        Summary::with_times(tree, TimeType::default())
+    }
+
+    /// Like [`Summary::of`], with the age range going by `time_type`.
+    pub fn with_times(tree: &Directory, time_type: TimeType) -> Self {
+        let mut summary = Summary::default();
+        summary.count(0);
+        summary.add_dir(tree, 1, time_type);
+        summary
+    }
+
+    /// `--depth-histogram`: `depth 0: 1, depth 1: 14, depth 2: 203`.
+    pub fn depth_histogram(&self) -> String {
+        self.by_depth
+            .iter()
+            .enumerate()
+            .map(|(depth, entries)| format!("depth {depth}: {entries}"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// `--age-summary`: `oldest: 2019-03-01, newest: 2024-06-12`, or `None` when no file
+    /// had a timestamp to go by.
+    pub fn age_range(&self, format: &DateFormat) -> Option<String> {
+        Some(format!(
+            "oldest: {}, newest: {}",
+            format.format(self.oldest?),
+            format.format(self.newest?)
+        ))
+    }
+
+    fn count(&mut self, depth: usize) {
+        if self.by_depth.len() <= depth {
+            self.by_depth.resize(depth + 1, 0);
+        }
+        self.by_depth[depth] += 1;
+    }
+
+    //`depth` is that of the directory's entries
+    fn add_dir(&mut self, dir: &Directory, depth: usize, time_type: TimeType) {
+        self.directories += 1;
+        for entry in dir.subdirectories.iter() {
+            self.count(depth);
+            match entry {
+                TreeEntry::FileNode(file) => {
+                    self.files += 1;
+                    self.bytes += file.size.unwrap_or(0);
+                    let time = file.metadata.as_ref().and_then(|m| time_type.of(m));
+                    if let Some(time) = time {
+                        self.oldest = Some(self.oldest.map_or(time, |t| t.min(time)));
+                        self.newest = Some(self.newest.map_or(time, |t| t.max(time)));
+                    }
+                }
+                TreeEntry::SymlinkNode(_) => self.symlinks += 1,
+                TreeEntry::DirNode(dir_entry) => self.add_dir(dir_entry, depth + 1, time_type),
+            }
+        }
+    }
+}
+
+/// `--skip-dir-over`: empties every directory below `dir` whose subtree holds more than
+/// `limit` bytes, leaving its size in `skipped`. Returns the size of `dir` itself.
+pub fn skip_dirs_over(dir: &mut Directory, limit: u64) -> u64 {
+    let mut bytes = 0;
+    for entry in dir.subdirectories.iter_mut() {
+        match entry {
+            TreeEntry::FileNode(file) => bytes += file.size.unwrap_or(0),
+            TreeEntry::SymlinkNode(_) => (),
+            TreeEntry::DirNode(dir_entry) => {
+                //post-order, so each subtree is only added up once
+                //a bundle that was never entered already knows its size
+                let dir_bytes = match dir_entry.skipped {
+                    Some(bytes) => bytes,
+                    None => skip_dirs_over(dir_entry, limit),
+                };
+                if dir_bytes > limit {
+                    dir_entry.subdirectories.clear();
+                    dir_entry.skipped = Some(dir_bytes);
+                }
+                bytes += dir_bytes;
+            }
+        }
+    }
+    bytes
+}
+
+/// A size in binary units, e.g. `4.2 GiB`; below 1 KiB it's whole bytes. The formatter's
+/// precision sets the decimals, one by default, so `format!("{:.0}", size)` gives `4 GiB`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Size(pub u64);
+
+impl std::fmt::Display for Size {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        const UNITS: [&str; 5] = ["KiB", "MiB", "GiB", "TiB", "PiB"];
+        if self.0 < 1024 {
+            return write!(f, "{} B", self.0);
+        }
+        let mut size = self.0 as f64 / 1024.0;
+        let mut unit = 0;
+        while size >= 1024.0 && unit + 1 < UNITS.len() {
+            size /= 1024.0;
+            unit += 1;
+        }
+        write!(f, "{size:.*} {}", f.precision().unwrap_or(1), UNITS[unit])
+    }
+}
+
+/// A size in SI units for `--si`, e.g. `4.5 GB` for 4,509,715,660 bytes; below 1 kB it's
+/// whole bytes. One decimal, like `Size`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SiSize(pub u64);
+
+impl std::fmt::Display for SiSize {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        const UNITS: [&str; 5] = ["kB", "MB", "GB", "TB", "PB"];
+        if self.0 < 1000 {
+            return write!(f, "{} B", self.0);
+        }
+        let mut size = self.0 as f64 / 1000.0;
+        let mut unit = 0;
+        while size >= 1000.0 && unit + 1 < UNITS.len() {
+            size /= 1000.0;
+            unit += 1;
+        }
+        write!(f, "{size:.1} {}", UNITS[unit])
+    }
+}
+
+impl std::fmt::Display for Summary {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{} files, {} directories, {} symlinks, {} bytes total",
+            self.files, self.directories, self.symlinks, self.bytes
+        )
+    }
+}
+
+#[test]
+fn human_sizes() {
+    assert_eq!(Size(0).to_string(), "0 B");
+    assert_eq!(Size(1023).to_string(), "1023 B");
+    assert_eq!(Size(1536).to_string(), "1.5 KiB");
+    assert_eq!(Size(4_509_715_660).to_string(), "4.2 GiB");
+    assert_eq!(format!("{:.0}", Size(4_509_715_660)), "4 GiB");
+    assert_eq!(format!("{:.0}", Size(1536)), "2 KiB");
+    assert_eq!(format!("{:.3}", Size(1536)), "1.500 KiB");
+    assert_eq!(format!("{:.2}", Size(1000)), "1000 B");
+
+    assert_eq!(SiSize(999).to_string(), "999 B");
+    assert_eq!(SiSize(1500).to_string(), "1.5 kB");
+    assert_eq!(SiSize(4_509_715_660).to_string(), "4.5 GB");
+}
+
+#[test]
+fn dirs_over_the_limit_are_emptied() {
+    use crate::{test_dir_node, test_file};
+
+    let mut tree = Directory {
+        name: "root".to_string(),
+        subdirectories: vec![
+            test_dir_node("src", vec![test_file("main.rs", Some(40))]),
+            test_dir_node(
+                "vendor",
+                vec![
+                    test_dir_node("big", vec![test_file("lib.a", Some(500))]),
+                    test_file("x", Some(20)),
+                ],
+            ),
+        ],
+        error: None,
+        skipped: None,
+    };
+
+    assert_eq!(skip_dirs_over(&mut tree, 100), 560);
+    let skipped: Vec<(&str, Option<u64>, usize)> = tree
+        .subdirectories
+        .iter()
+        .map(|entry| match entry {
+            TreeEntry::DirNode(dir) => (dir.name.as_str(), dir.skipped, dir.subdirectories.len()),
+            other => panic!("expected a directory, got {other:?}"),
+        })
+        .collect();
+    assert_eq!(skipped, vec![("src", None, 1), ("vendor", Some(520), 0)]);
+    assert_eq!(
+        Summary::of(&tree).depth_histogram(),
+        "depth 0: 1, depth 1: 2, depth 2: 1"
+    );
+    assert_eq!(tree.skipped, None);
+}
+
+#[test]
+fn age_range_spans_file_times() {
+    use std::{
+        fs::FileTimes,
+        time::{Duration, UNIX_EPOCH},
+    };
+
+    use crate::{File, FileKind};
+
+    let dir = crate::test_dir("age_range");
+    //each file was last read a day after it was modified
+    let file = |name: &str, seconds: u64| {
+        let path = dir.join(name);
+        let modified = UNIX_EPOCH + Duration::from_secs(seconds);
+        let times = FileTimes::new()
+            .set_modified(modified)
+            .set_accessed(modified + Duration::from_secs(24 * 60 * 60));
+        std::fs::File::create(&path)
+            .unwrap()
+            .set_times(times)
+            .unwrap();
+        TreeEntry::FileNode(File {
+            name: name.to_string(),
+            metadata: std::fs::metadata(&path).ok(),
+            kind: FileKind::Regular,
+            path,
+            size: Some(0),
+        })
+    };
+    let mut tree = Directory {
+        name: "root".to_string(),
+        subdirectories: vec![file("new", 1_718_150_400), file("old", 1_551_398_400)],
+        error: None,
+        skipped: None,
+    };
+    let format = "%F".parse().unwrap();
+    assert_eq!(
+        Summary::of(&tree).age_range(&format).unwrap(),
+        "oldest: 2019-03-01, newest: 2024-06-12"
+    );
+    assert_eq!(
+        Summary::with_times(&tree, TimeType::Accessed)
+            .age_range(&format)
+            .unwrap(),
+        "oldest: 2019-03-02, newest: 2024-06-13"
+    );
+    for entry in tree.subdirectories.iter_mut() {
+        if let TreeEntry::FileNode(file) = entry {
+            file.metadata = None;
+        }
+    }
+    assert_eq!(Summary::of(&tree).age_range(&format), None);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,94 @@
+use std::{
+    cmp::Reverse,
+    collections::BinaryHeap,
+    io::{self, Write},
+};
+
+use crate::{quoted, summary::Size, Directory, TreeEntry};
+
+/// The `n` biggest files in the tree as `/`-separated paths relative to the root with their
+/// sizes, largest first and in walk order among equal sizes. Only `n` files are held while
+/// looking, so the rest of the tree is never sorted. Files without a known size don't count.
+pub fn largest_files(tree: &Directory, n: usize) -> Vec<(String, u64)> {
+    //a min-heap, so the smallest of the kept files is the one to give up
+    let mut heap = BinaryHeap::with_capacity(n + 1);
+    let mut order = 0;
+    collect(tree, "", n, &mut order, &mut heap);
+    return heap
+        .into_sorted_vec()
+        .into_iter()
+        .map(|(Reverse(size), _, path)| (path, size))
+        .collect();
+
+    fn collect(
+        dir: &Directory,
+        pre: &str,
+        n: usize,
+        order: &mut usize,
+        heap: &mut BinaryHeap<(Reverse<u64>, usize, String)>,
+    ) {
+        for entry in dir.subdirectories.iter() {
+            match entry {
+                TreeEntry::DirNode(dir_entry) => collect(
+                    dir_entry,
+                    &format!("{}{}/", pre, dir_entry.name),
+                    n,
+                    order,
+                    heap,
+                ),
+                TreeEntry::FileNode(file) => {
+                    let Some(size) = file.size.filter(|_| n > 0) else {
+                        continue;
+                    };
+                    *order += 1;
+                    heap.push((Reverse(size), *order, format!("{}{}", pre, file.name)));
+                    if heap.len() > n {
+                        heap.pop();
+                    }
+                }
+                TreeEntry::SymlinkNode(_) => (),
+            }
+        }
+    }
+}
+
+/// `--top`: one line per file, its human-readable size right-aligned before the path.
+pub fn print_top(out: &mut dyn Write, tree: &Directory, n: usize, quote: bool) -> io::Result<()> {
+    for (path, size) in largest_files(tree, n) {
+        let path = if quote { quoted(&path) } else { path };
+        writeln!(out, "{:>10}  {path}", Size(size).to_string())?;
+    }
+    Ok(())
+}
+
+#[test]
+fn keeps_only_the_largest() {
+    use crate::{test_dir_node, test_file};
+
+    let tree = Directory {
+        name: "root".to_string(),
+        subdirectories: vec![
+            test_file("small", Some(1)),
+            test_dir_node(
+                "src",
+                vec![test_file("big", Some(5000)), test_file("tie", Some(3))],
+            ),
+            test_file("medium", Some(3)),
+        ],
+        error: None,
+        skipped: None,
+    };
+
+    assert_eq!(
+        largest_files(&tree, 3),
+        vec![
+            ("src/big".to_string(), 5000),
+            ("src/tie".to_string(), 3),
+            ("medium".to_string(), 3)
+        ]
+    );
+    assert!(largest_files(&tree, 0).is_empty());
+    let mut out = Vec::new();
+    print_top(&mut out, &tree, 1, false).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "   4.9 KiB  src/big\n");
+}
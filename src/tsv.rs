@@ -0,0 +1,86 @@
+use std::{
+    fs::Metadata,
+    io::{self, Write},
+};
+
+use crate::{date::DateFormat, Directory, TreeEntry};
+
+/// Prints a header and then one tab-separated row per entry:
+/// `depth`, `type`, `path`, `size` and `modified`. Depth 1 is directly below the root, paths are
+/// relative and `/`-separated, sizes are in bytes and times are ISO-8601 in UTC.
+/// Values that aren't known, like a directory's size, are left empty.
+pub fn print_tsv(out: &mut dyn Write, tree: &Directory) -> io::Result<()> {
+    writeln!(out, "depth\ttype\tpath\tsize\tmodified")?;
+    return visit(out, tree, "", 1, &DateFormat::default());
+
+    fn visit(
+        out: &mut dyn Write,
+        dir: &Directory,
+        pre: &str,
+        depth: usize,
+        date_format: &DateFormat,
+    ) -> io::Result<()> {
+        for entry in dir.subdirectories.iter() {
+            let path = format!("{}{}", pre, entry.name());
+            let modified = |metadata: Option<&Metadata>| {
+                metadata
+                    .and_then(|metadata| metadata.modified().ok())
+                    .map(|modified| date_format.format(modified))
+                    .unwrap_or_default()
+            };
+            let (kind, size, modified) = match entry {
+                TreeEntry::DirNode(_) => ("directory", None, String::new()),
+                TreeEntry::FileNode(file) => ("file", file.size, modified(file.metadata.as_ref())),
+                TreeEntry::SymlinkNode(link) => {
+                    ("symlink", None, modified(link.link_metadata.as_ref()))
+                }
+            };
+            writeln!(
+                out,
+                "{}\t{}\t{}\t{}\t{}",
+                depth,
+                kind,
+                escape(&path),
+                size.map(|size| size.to_string()).unwrap_or_default(),
+                modified
+            )?;
+            if let TreeEntry::DirNode(dir_entry) = entry {
+                visit(out, dir_entry, &format!("{path}/"), depth + 1, date_format)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+//keeps every row on one line with the right number of columns
+fn escape(field: &str) -> String {
+    field
+        .replace('\\', r"\\")
+        .replace('\t', r"\t")
+        .replace('\n', r"\n")
+        .replace('\r', r"\r")
+}
+
+#[test]
+fn one_row_per_entry() {
+    use crate::{test_dir_node, test_file};
+
+    let tree = Directory {
+        name: "root".to_string(),
+        subdirectories: vec![test_dir_node(
+            "src",
+            vec![test_file("tab\there.rs", Some(42))],
+        )],
+        error: None,
+        skipped: None,
+    };
+    let mut out = Vec::new();
+    print_tsv(&mut out, &tree).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "depth\ttype\tpath\tsize\tmodified
+1\tdirectory\tsrc\t\t
+2\tfile\tsrc/tab\\there.rs\t42\t
+"
+    );
+}
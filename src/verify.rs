@@ -0,0 +1,143 @@
+use std::{collections::BTreeMap, fs, io::Write, path::Path};
+
+use anyhow::{bail, Context, Result};
+
+use crate::{export::Node, hash::HashAlgorithm, Directory};
+
+#[derive(Debug, PartialEq)]
+enum Kind<'a> {
+    Directory,
+    //size and --hash digest
+    File(Option<u64>, Option<&'a str>),
+    Symlink(&'a str),
+}
+
+impl Kind<'_> {
+    fn label(&self) -> &'static str {
+        match self {
+            Kind::Directory => "directory",
+            Kind::File(..) => "file",
+            Kind::Symlink(_) => "symlink",
+        }
+    }
+}
+
+//every entry below the root keyed by its `/`-separated relative path
+fn flatten<'a>(node: &'a Node, prefix: &str, entries: &mut BTreeMap<String, Kind<'a>>) {
+    if let Node::Directory { children, .. } = node {
+        for child in children {
+            let (name, kind) = match child {
+                Node::Directory { name, .. } => (name, Kind::Directory),
+                Node::File { name, size, hash } => (name, Kind::File(*size, hash.as_deref())),
+                Node::Symlink { name, target } => (name, Kind::Symlink(target)),
+            };
+            let path = format!("{prefix}{name}");
+            flatten(child, &format!("{path}/"), entries);
+            entries.insert(path, kind);
+        }
+    }
+}
+
+/// One line per entry that is missing, extra or different in `current` compared to `manifest`.
+/// The root names aren't compared, so a manifest still applies after the directory is moved.
+fn discrepancies(manifest: &Node, current: &Node) -> Vec<String> {
+    let mut expected = BTreeMap::new();
+    flatten(manifest, "", &mut expected);
+    let mut actual = BTreeMap::new();
+    flatten(current, "", &mut actual);
+
+    let mut found = Vec::new();
+    for (path, kind) in &expected {
+        match (kind, actual.get(path)) {
+            (_, None) => found.push(format!("missing: {path}")),
+            (Kind::File(Some(old), _), Some(Kind::File(Some(new), _))) if old != new => {
+                found.push(format!("size changed: {path} ({old} -> {new} bytes)"))
+            }
+            //only when both sides were hashed, so a manifest saved with --hash still applies
+            //without it
+            (Kind::File(_, Some(old)), Some(Kind::File(_, Some(new)))) if old != new => {
+                found.push(format!("content changed: {path} ({old} -> {new})"))
+            }
+            (Kind::Symlink(old), Some(Kind::Symlink(new))) if old != new => {
+                found.push(format!("target changed: {path} ({old} -> {new})"))
+            }
+            (kind, Some(other)) if kind.label() != other.label() => found.push(format!(
+                "type changed: {path} ({} -> {})",
+                kind.label(),
+                other.label()
+            )),
+            _ => (),
+        }
+    }
+    for path in actual.keys() {
+        if !expected.contains_key(path) {
+            found.push(format!("extra: {path}"));
+        }
+    }
+    found
+}
+
+/// Checks the walked `tree` against a manifest saved with `--json`, listing every difference
+/// and failing when there is any. With `hash`, file digests are compared too.
+pub fn verify(
+    out: &mut dyn Write,
+    tree: &Directory,
+    manifest_path: &Path,
+    hash: Option<HashAlgorithm>,
+) -> Result<()> {
+    let manifest = fs::read_to_string(manifest_path)
+        .with_context(|| format!("Cannot read manifest {}", manifest_path.display()))?;
+    let manifest: Node = serde_json::from_str(&manifest)
+        .with_context(|| format!("Invalid manifest {}", manifest_path.display()))?;
+
+    let found = discrepancies(&manifest, &Node::from_dir(tree, hash));
+    for line in &found {
+        writeln!(out, "{line}")?;
+    }
+    if !found.is_empty() {
+        bail!(
+            "{} difference(s) from {}",
+            found.len(),
+            manifest_path.display()
+        );
+    }
+    writeln!(out, "matches {}", manifest_path.display())?;
+    Ok(())
+}
+
+#[test]
+fn reports_missing_extra_and_changed() {
+    let manifest: Node = serde_json::from_str(
+        r#"{"type":"directory","name":"old","children":[
+            {"type":"file","name":"kept.txt","size":3,"hash":"352441c2"},
+            {"type":"file","name":"edited.txt","size":3,"hash":"352441c2"},
+            {"type":"file","name":"grown.txt","size":3},
+            {"type":"directory","name":"gone","children":[{"type":"file","name":"a"}]},
+            {"type":"symlink","name":"link","target":"kept.txt"}
+        ]}"#,
+    )
+    .unwrap();
+    let current: Node = serde_json::from_str(
+        r#"{"type":"directory","name":"new","children":[
+            {"type":"file","name":"kept.txt","size":3},
+            {"type":"file","name":"edited.txt","size":3,"hash":"8587d865"},
+            {"type":"file","name":"grown.txt","size":5},
+            {"type":"file","name":"link"},
+            {"type":"file","name":"added.txt","size":0}
+        ]}"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        discrepancies(&manifest, &current),
+        vec![
+            "content changed: edited.txt (352441c2 -> 8587d865)",
+            "missing: gone",
+            "missing: gone/a",
+            "size changed: grown.txt (3 -> 5 bytes)",
+            "type changed: link (symlink -> file)",
+            "extra: added.txt",
+        ]
+    );
+    assert!(discrepancies(&manifest, &manifest).is_empty());
+}